@@ -1,4 +1,4 @@
-use crate::{init::Init, shell::initialization};
+use crate::{check_rules::check_rules, init::Init, modules::list_modules, shell::initialization};
 use colored::Colorize;
 
 pub enum Status {
@@ -36,6 +36,22 @@ pub fn handle_args(args: impl IntoIterator<Item = String>) -> Status {
 				_ => init.alias = String::from("f"),
 			},
 			"--nocnf" => init.cnf = false,
+			"check-rules" => {
+				if !check_rules(iter.next()) {
+					std::process::exit(1);
+				}
+				return Status::Exit;
+			}
+			"modules" => match iter.next().as_deref() {
+				Some("list") => {
+					list_modules();
+					return Status::Exit;
+				}
+				_ => {
+					eprintln!("Usage: pay-respects modules list");
+					return Status::Error;
+				}
+			},
 			_ => init.shell = arg,
 		}
 	}
@@ -54,12 +70,17 @@ fn print_help() {
 		"{}",
 		t!(
 			"help",
-			usage = "pay-respects <shell> [--alias [<alias>]] [--nocnf]",
-			eval = "Bash / Zsh / Fish".bold().to_string(),
+			usage = "pay-respects <shell> [--alias [<alias>]] [--nocnf]\n       pay-respects check-rules [<path>]\n       pay-respects modules list",
+			eval = "Bash / Zsh / Fish / POSIX sh / Elvish / Xonsh"
+				.bold()
+				.to_string(),
 			eval_examples = r#"
 eval "$(pay-respects bash)"
 eval "$(pay-respects zsh)"
 pay-respects fish | source
+eval "$(pay-respects sh)"
+eval (pay-respects elvish | slurp)
+execx($(pay-respects xonsh))
 "#,
 			manual = "Nushell / PowerShell".bold().to_string(),
 			manual_examples = r#"
@@ -110,6 +131,11 @@ mod tests {
 		for args in [
 			[String::new(), String::from("fish"), String::from("--alias")],
 			[String::new(), String::from("bash"), String::from("--nocnf")],
+			[
+				String::new(),
+				String::from("xonsh"),
+				String::from("--nocnf"),
+			],
 		] {
 			println!("Arguments {:?} should return Exit", args);
 			assert!(matches!(handle_args(args), Status::Exit));
@@ -119,6 +145,7 @@ mod tests {
 			[String::new(), String::from("-a")],
 			[String::new(), String::from("--alias")],
 			[String::new(), String::from("--nocnf")],
+			[String::new(), String::from("modules")],
 		] {
 			println!("Arguments {:?} should return Error", args);
 			assert!(matches!(handle_args(args), Status::Error));
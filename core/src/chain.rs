@@ -0,0 +1,260 @@
+use std::ops::Range;
+
+use pay_respects_utils::evals::split_command;
+use pay_respects_utils::lists::privilege_list;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+	Pipe,
+	PipeAll,
+	And,
+	Or,
+	Sequence,
+}
+
+/// A command in a pipeline or list, located by its byte range in the
+/// original line so the rest of the line can be kept untouched
+#[derive(Debug, Clone)]
+pub struct Segment {
+	pub range: Range<usize>,
+	/// Operator following this segment, `None` for the last one
+	pub operator: Option<Operator>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Chain {
+	pub line: String,
+	pub segments: Vec<Segment>,
+}
+
+impl Chain {
+	/// Parses a command line into its pipeline/list segments
+	/// Returns `None` if the line is a single command
+	pub fn parse(line: &str) -> Option<Chain> {
+		let bytes = line.as_bytes();
+		let mut segments = vec![];
+		let mut start = 0;
+		let mut quote: Option<u8> = None;
+		let mut depth: i32 = 0;
+		let mut i = 0;
+
+		while i < bytes.len() {
+			let c = bytes[i];
+			// backslashes are literal inside single quotes
+			if c == b'\\' && quote != Some(b'\'') {
+				i += 2;
+				continue;
+			}
+			if let Some(q) = quote {
+				if c == q {
+					quote = None;
+				}
+				i += 1;
+				continue;
+			}
+			match c {
+				b'\'' | b'"' | b'`' => quote = Some(c),
+				b'(' | b'{' => depth += 1,
+				b')' | b'}' => depth = (depth - 1).max(0),
+				b'|' | b'&' | b';' if depth == 0 => {
+					let next = bytes.get(i + 1).copied();
+					let (operator, len) = match (c, next) {
+						(b'|', Some(b'|')) => (Operator::Or, 2),
+						(b'|', Some(b'&')) => (Operator::PipeAll, 2),
+						(b'|', _) => (Operator::Pipe, 1),
+						(b'&', Some(b'&')) => (Operator::And, 2),
+						(b';', Some(b';')) => {
+							// case terminator, not a list
+							return None;
+						}
+						(b';', _) => (Operator::Sequence, 1),
+						_ => {
+							// background job or redirection such as `2>&1`
+							i += 1;
+							continue;
+						}
+					};
+					if !push_segment(&mut segments, line, start..i, Some(operator)) {
+						return None;
+					}
+					i += len;
+					start = i;
+					continue;
+				}
+				_ => {}
+			}
+			i += 1;
+		}
+
+		if quote.is_some() {
+			return None;
+		}
+		// trailing `;` is allowed, trailing `&&` or `|` is an incomplete line
+		if !push_segment(&mut segments, line, start..bytes.len(), None) {
+			match segments.last() {
+				Some(segment) if segment.operator == Some(Operator::Sequence) => {
+					segments.last_mut().unwrap().operator = None;
+				}
+				_ => return None,
+			}
+		}
+
+		if segments.len() < 2 {
+			return None;
+		}
+		Some(Chain {
+			line: line.to_string(),
+			segments,
+		})
+	}
+
+	pub fn segment(&self, index: usize) -> &str {
+		&self.line[self.segments[index].range.clone()]
+	}
+
+	/// Rebuilds the full line with only the given segment changed
+	pub fn replace(&self, index: usize, command: &str) -> String {
+		let range = &self.segments[index].range;
+		format!(
+			"{}{}{}",
+			&self.line[..range.start],
+			command.trim(),
+			&self.line[range.end..]
+		)
+	}
+
+	/// Guesses which segment produced the error:
+	/// - The first segment whose executable cannot be found
+	/// - Otherwise the last segment whose executable is mentioned in the error
+	/// - Otherwise the last segment
+	pub fn failing_segment(&self, error: &str, executables: &[String]) -> usize {
+		let names = (0..self.segments.len())
+			.map(|i| segment_executable(self.segment(i)))
+			.collect::<Vec<String>>();
+
+		for (i, name) in names.iter().enumerate() {
+			if name.is_empty() || name.contains(std::path::MAIN_SEPARATOR) {
+				continue;
+			}
+			if !executables.contains(name) {
+				return i;
+			}
+		}
+
+		let error = error.to_lowercase();
+		for (i, name) in names.iter().enumerate().rev() {
+			if name.is_empty() {
+				continue;
+			}
+			let name = name.to_lowercase();
+			if error
+				.split(|c: char| c.is_whitespace() || c == ':' || c == '`' || c == '\'')
+				.any(|word| word == name)
+			{
+				return i;
+			}
+		}
+		self.segments.len() - 1
+	}
+}
+
+/// Executable of a segment, skipping environment variables and privilege
+fn segment_executable(segment: &str) -> String {
+	let privileges = privilege_list();
+	split_command(segment)
+		.into_iter()
+		.find(|token| {
+			let is_env = token.find('=').is_some_and(|i| i > 0);
+			!is_env && !privileges.contains(&token.as_str())
+		})
+		.map(|token| {
+			token
+				.rsplit(std::path::MAIN_SEPARATOR)
+				.next()
+				.unwrap()
+				.to_string()
+		})
+		.unwrap_or_default()
+}
+
+fn push_segment(
+	segments: &mut Vec<Segment>,
+	line: &str,
+	range: Range<usize>,
+	operator: Option<Operator>,
+) -> bool {
+	let range = trim_range(line, range);
+	if range.is_empty() {
+		return false;
+	}
+	segments.push(Segment { range, operator });
+	true
+}
+
+fn trim_range(line: &str, range: Range<usize>) -> Range<usize> {
+	let slice = &line[range.clone()];
+	let start = range.start + (slice.len() - slice.trim_start().len());
+	let end = range.end - (slice.len() - slice.trim_end().len());
+	if start > end {
+		start..start
+	} else {
+		start..end
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Chain, Operator};
+
+	#[test]
+	fn test_parse_chain() {
+		assert!(Chain::parse("git status").is_none());
+		assert!(Chain::parse("echo 'a && b'").is_none());
+		assert!(Chain::parse("cmd 2>&1").is_none());
+		assert!(Chain::parse("ls |").is_none());
+
+		let chain = Chain::parse(r#"echo "1\n2" | grepp 2"#).unwrap();
+		assert_eq!(chain.segments.len(), 2);
+		assert_eq!(chain.segment(0), r#"echo "1\n2""#);
+		assert_eq!(chain.segment(1), "grepp 2");
+		assert_eq!(chain.segments[0].operator, Some(Operator::Pipe));
+
+		let chain = Chain::parse(r"echo 'a\' | grepp x").unwrap();
+		assert_eq!(chain.segment(0), r"echo 'a\'");
+		assert_eq!(chain.segment(1), "grepp x");
+		assert!(Chain::parse(r"echo a\| grepp x").is_none());
+
+		let chain = Chain::parse("cd foo&&carg build || echo $(a | b); ls;").unwrap();
+		assert_eq!(chain.segments.len(), 4);
+		assert_eq!(chain.segment(2), "echo $(a | b)");
+		assert_eq!(chain.segments[1].operator, Some(Operator::Or));
+		assert_eq!(chain.segments[3].operator, None);
+		assert_eq!(
+			chain.replace(1, "cargo build"),
+			"cd foo&&cargo build || echo $(a | b); ls;"
+		);
+	}
+
+	#[test]
+	fn test_failing_segment() {
+		let executables = vec!["cd".to_string(), "cargo".to_string(), "echo".to_string()];
+
+		let chain = Chain::parse("cd foo && carg build").unwrap();
+		assert_eq!(
+			chain.failing_segment("carg: command not found", &executables),
+			1
+		);
+
+		let chain = Chain::parse("cd foo && cargo biuld").unwrap();
+		assert_eq!(
+			chain.failing_segment("error: no such command", &executables),
+			1
+		);
+
+		let chain = Chain::parse("cd foo && echo done").unwrap();
+		assert_eq!(
+			chain.failing_segment("cd: foo: No such file or directory", &executables),
+			0
+		);
+	}
+}
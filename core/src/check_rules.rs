@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use colored::Colorize;
+use pay_respects_ruleset::check_suggest;
+use pay_respects_utils::files::{get_path_files, rule_dirs};
+use serde::Deserialize;
+use toml::Spanned;
+
+use crate::shell::{alias_map, builtin_commands};
+
+#[derive(Deserialize)]
+struct Rule {
+	extends: Option<Vec<Spanned<String>>>,
+	#[serde(default)]
+	match_err: Vec<MatchError>,
+}
+
+#[derive(Deserialize)]
+struct MatchError {
+	suggest: Vec<Spanned<String>>,
+}
+
+struct Problem {
+	line: usize,
+	message: String,
+	warning: bool,
+}
+
+/// Checks the rule file or directory at `path`, or every rule directory if
+/// not given. Returns whether no errors were found.
+pub fn check_rules(path: Option<String>) -> bool {
+	let files = match path {
+		Some(path) if Path::new(&path).is_file() => vec![path],
+		Some(path) => rule_files(&path),
+		None => rule_dirs().iter().flat_map(|dir| rule_files(dir)).collect(),
+	};
+	if files.is_empty() {
+		eprintln!("No rule files found");
+		return true;
+	}
+
+	let commands = known_commands();
+	let mut errors = 0;
+	let mut warnings = 0;
+	for file in &files {
+		for problem in check_file(file, &commands) {
+			let level = if problem.warning {
+				warnings += 1;
+				"warning".yellow().bold()
+			} else {
+				errors += 1;
+				"error".red().bold()
+			};
+			println!("{}:{}: {}: {}", file, problem.line, level, problem.message);
+		}
+	}
+	println!(
+		"Checked {} files: {} errors, {} warnings",
+		files.len(),
+		errors,
+		warnings
+	);
+	errors == 0
+}
+
+fn rule_files(dir: &str) -> Vec<String> {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(_) => return vec![],
+	};
+	let mut files = entries
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+		.map(|path| path.to_string_lossy().to_string())
+		.collect::<Vec<String>>();
+	files.sort_unstable();
+	files
+}
+
+/// Commands a rule can be reached with: executables, and the builtins and
+/// aliases of the shell when run through its alias
+fn known_commands() -> Vec<String> {
+	let mut commands = get_path_files();
+	if let Ok(shell) = std::env::var("_PR_SHELL") {
+		commands.extend(builtin_commands(&shell));
+		commands.extend(alias_map(&shell).unwrap_or_default().into_keys());
+	}
+	commands
+}
+
+fn check_file(file: &str, commands: &[String]) -> Vec<Problem> {
+	let content = match std::fs::read_to_string(file) {
+		Ok(content) => content,
+		Err(err) => {
+			return vec![Problem {
+				line: 0,
+				message: err.to_string(),
+				warning: false,
+			}];
+		}
+	};
+	let rule: Rule = match toml::from_str(&content) {
+		Ok(rule) => rule,
+		Err(err) => {
+			let line = err
+				.span()
+				.map(|span| line_at(&content, span.start))
+				.unwrap_or(0);
+			return vec![Problem {
+				line,
+				message: err.message().to_string(),
+				warning: false,
+			}];
+		}
+	};
+
+	let mut problems = vec![];
+	for suggest in rule.match_err.iter().flat_map(|x| &x.suggest) {
+		let span = suggest.span();
+		for (snippet, message) in check_suggest(suggest.get_ref()) {
+			// locate the snippet inside the string literal
+			let offset = content[span.clone()]
+				.find(&snippet)
+				.map(|offset| span.start + offset)
+				.unwrap_or(span.start);
+			problems.push(Problem {
+				line: line_at(&content, offset),
+				message,
+				warning: false,
+			});
+		}
+	}
+
+	let rule_name = Path::new(file)
+		.file_stem()
+		.map(|stem| stem.to_string_lossy().to_string())
+		.unwrap_or_default();
+	for target in rule.extends.iter().flatten() {
+		let name = target.get_ref();
+		let message = if name == &rule_name {
+			format!("rule extends its own name `{}`", name)
+		} else if !commands.contains(name) {
+			format!(
+				"extends target `{}` is not an executable, builtin or alias, the rule is not reached through it",
+				name
+			)
+		} else {
+			continue;
+		};
+		problems.push(Problem {
+			line: line_at(&content, target.span().start),
+			message,
+			warning: true,
+		});
+	}
+	problems
+}
+
+fn line_at(content: &str, offset: usize) -> usize {
+	content[..offset.min(content.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{check_file, rule_files};
+
+	fn messages(file: &str, commands: &[&str]) -> Vec<String> {
+		let commands = commands.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+		check_file(file, &commands)
+			.into_iter()
+			.map(|problem| format!("{}: {}", problem.line, problem.message))
+			.collect()
+	}
+
+	#[test]
+	fn test_shipped_rules() {
+		let files = rule_files(concat!(env!("CARGO_MANIFEST_DIR"), "/rules"));
+		assert!(!files.is_empty());
+		for file in files {
+			// extends targets are tools that may not be installed
+			let problems = messages(&file, &[])
+				.into_iter()
+				.filter(|message| !message.contains("is not an executable"))
+				.collect::<Vec<String>>();
+			assert!(problems.is_empty(), "{}: {:?}", file, problems);
+		}
+	}
+
+	#[test]
+	fn test_extends() {
+		let dir = std::env::temp_dir().join(format!("pay-respects-check-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let file = dir.join("hello.toml");
+		std::fs::write(
+			&file,
+			"command = \"hello\"\nextends = [\"hello\", \"goodbye\", \"hi\"]\n",
+		)
+		.unwrap();
+
+		assert_eq!(
+			messages(&file.to_string_lossy(), &["hello", "hi"]),
+			[
+				"2: rule extends its own name `hello`",
+				"2: extends target `goodbye` is not an executable, builtin or alias, the rule is not reached through it",
+			]
+		);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+
 use pay_respects_utils::strings::print_error;
 use serde::Deserialize;
 
 use pay_respects_utils::files::config_files;
+use pay_respects_utils::redact::{RedactConfigReader, Redactor};
 use pay_respects_utils::{merge, merge_option};
 
 #[allow(dead_code)]
@@ -12,7 +15,12 @@ pub struct ConfigReader {
 	pub timeout: Option<u64>,
 	pub blocking_commands: Option<Vec<String>>,
 	pub eval_method: Option<EvalMethod>,
+	pub capture_method: Option<CaptureMethod>,
 	pub package_manager: Option<PackageManagerConfig>,
+	pub history: Option<HistoryConfigReader>,
+	pub rerun: Option<RerunConfigReader>,
+	pub redact: Option<RedactConfigReader>,
+	pub modules: Option<ModulesConfigReader>,
 }
 
 #[allow(dead_code)]
@@ -22,6 +30,41 @@ pub struct PackageManagerConfig {
 	pub install_method: Option<InstallMethod>,
 }
 
+#[derive(Deserialize, Default)]
+pub struct HistoryConfigReader {
+	pub enabled: Option<bool>,
+	pub max_entries: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RerunConfigReader {
+	pub allow: Option<Vec<String>>,
+	pub deny: Option<Vec<String>>,
+	pub sandbox: Option<SandboxMode>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ModulesConfigReader {
+	pub timeout: Option<u64>,
+	/// Settings of a single module, by name
+	#[serde(flatten)]
+	pub modules: HashMap<String, ModuleConfigReader>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct ModuleConfigReader {
+	pub enabled: Option<bool>,
+	/// Replaces the priority in the file name
+	pub priority: Option<u32>,
+	pub timeout: Option<u64>,
+	/// Only run for these executables
+	pub executables: Option<Vec<String>>,
+	/// Only run in these modes, see `Mode::name`
+	pub modes: Option<Vec<String>>,
+	/// Passed to the module as is
+	pub settings: Option<toml::Table>,
+}
+
 #[derive(Deserialize, Default, PartialEq)]
 pub enum InstallMethod {
 	#[default]
@@ -37,14 +80,105 @@ pub enum EvalMethod {
 	Shell,
 }
 
+#[derive(Deserialize, Default, PartialEq)]
+pub enum CaptureMethod {
+	#[default]
+	Pipe,
+	Pty,
+}
+
+#[derive(Deserialize, Default, PartialEq)]
+pub enum SandboxMode {
+	#[default]
+	Never,
+	Denied,
+	Always,
+}
+
 pub struct Config {
 	pub privilege: Option<String>,
 	pub merge_commands: Option<Vec<Vec<String>>>,
 	pub timeout: u64,
 	pub blocking_commands: Option<Vec<String>>,
 	pub eval_method: EvalMethod,
+	pub capture_method: CaptureMethod,
 	pub package_manager: Option<String>,
 	pub install_method: InstallMethod,
+	pub history: HistoryConfig,
+	pub rerun: RerunConfig,
+	pub redact: RedactConfig,
+	pub modules: ModulesConfig,
+}
+
+pub struct HistoryConfig {
+	pub enabled: bool,
+	pub max_entries: usize,
+}
+
+impl Default for HistoryConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			max_entries: 1000,
+		}
+	}
+}
+
+#[derive(Default)]
+pub struct RerunConfig {
+	pub allow: Vec<String>,
+	pub deny: Vec<String>,
+	pub sandbox: SandboxMode,
+}
+
+pub struct RedactConfig {
+	pub enabled: bool,
+	pub patterns: Vec<String>,
+}
+
+impl Default for RedactConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			patterns: vec![],
+		}
+	}
+}
+
+impl RedactConfig {
+	/// Redactor for data passed to modules, if enabled
+	pub fn redactor(&self) -> Option<Redactor> {
+		if !self.enabled {
+			return None;
+		}
+		// invalid patterns are removed when loading
+		Redactor::new(&self.patterns).ok()
+	}
+}
+
+pub struct ModulesConfig {
+	/// Milliseconds before a module is killed
+	pub timeout: u64,
+	pub modules: HashMap<String, ModuleConfigReader>,
+}
+
+impl Default for ModulesConfig {
+	fn default() -> Self {
+		Self {
+			timeout: 30000,
+			modules: HashMap::new(),
+		}
+	}
+}
+
+impl ModulesConfig {
+	/// Timeout of the module named `name`, see `modules::module_name`
+	pub fn timeout(&self, name: &str) -> u64 {
+		self.modules
+			.get(name)
+			.and_then(|module| module.timeout)
+			.unwrap_or(self.timeout)
+	}
 }
 
 impl Default for Config {
@@ -55,8 +189,13 @@ impl Default for Config {
 			timeout: 3000,
 			blocking_commands: None,
 			eval_method: EvalMethod::Internal,
+			capture_method: CaptureMethod::Pipe,
 			package_manager: None,
 			install_method: InstallMethod::Default,
+			history: HistoryConfig::default(),
+			rerun: RerunConfig::default(),
+			redact: RedactConfig::default(),
+			modules: ModulesConfig::default(),
 		}
 	}
 }
@@ -64,12 +203,45 @@ impl Default for Config {
 impl Config {
 	pub fn merge(&mut self, reader: ConfigReader) {
 		merge_option!(self, reader, privilege, merge_commands, blocking_commands);
-		merge!(self, reader, timeout, eval_method);
+		merge!(self, reader, timeout, eval_method, capture_method);
 
 		if let Some(reader) = reader.package_manager {
 			merge_option!(self, reader, package_manager);
 			merge!(self, reader, install_method);
 		}
+
+		if let Some(reader) = reader.history {
+			let history = &mut self.history;
+			merge!(history, reader, enabled, max_entries);
+		}
+
+		if let Some(reader) = reader.rerun {
+			let rerun = &mut self.rerun;
+			merge!(rerun, reader, allow, deny, sandbox);
+		}
+
+		if let Some(reader) = reader.redact {
+			let redact = &mut self.redact;
+			merge!(redact, reader, enabled, patterns);
+		}
+
+		if let Some(reader) = reader.modules {
+			let modules = &mut self.modules;
+			merge!(modules, reader, timeout);
+			for (name, module) in reader.modules {
+				let config = modules.modules.entry(name).or_default();
+				merge_option!(
+					config,
+					module,
+					enabled,
+					priority,
+					timeout,
+					executables,
+					modes,
+					settings
+				);
+			}
+		}
 	}
 
 	pub fn set_package_manager(&mut self, package_manager: &str) {
@@ -105,5 +277,14 @@ pub fn load_config() -> Config {
 		});
 		config.merge(reader);
 	}
+	config.redact.patterns.retain(
+		|pattern| match Redactor::new(std::slice::from_ref(pattern)) {
+			Ok(_) => true,
+			Err(e) => {
+				print_error(&e);
+				false
+			}
+		},
+	);
 	config
 }
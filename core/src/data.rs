@@ -1,7 +1,5 @@
 use pay_respects_utils::evals::split_command;
 use pay_respects_utils::evals::split_comment;
-use pay_respects_utils::files::get_path_files;
-use pay_respects_utils::files::path_env_sep;
 use pay_respects_utils::lists::privilege_list;
 use pay_respects_utils::modes::Mode;
 use pay_respects_utils::modes::run_mode;
@@ -12,12 +10,15 @@ use pay_respects_utils::strings::remove_color_codes;
 use std::process::exit;
 
 use std::collections::HashMap;
+use std::sync::Mutex;
 
-#[cfg(windows)]
-use pay_respects_utils::files::path_convert;
+use pay_respects_ruleset::RuleSet;
 
 use crate::config::Config;
 use crate::config::load_config;
+use crate::modules::arrange;
+use crate::modules::discover;
+use crate::modules::lib_dir;
 use crate::shell::alias_map;
 use crate::shell::builtin_commands;
 use crate::shell::expand_alias_multiline;
@@ -25,6 +26,53 @@ use crate::shell::get_error;
 use crate::shell::get_shell;
 use crate::shell::last_command;
 
+#[derive(Clone, Debug)]
+pub enum Source {
+	Rule(String),
+	Module(String),
+	Fallback(String),
+}
+
+/// Where a candidate comes from
+#[derive(Clone, Debug)]
+pub struct Origin {
+	pub source: Source,
+	/// Error pattern of the rule that matched
+	pub pattern: Option<String>,
+	/// Rule file the candidate was generated from
+	pub file: Option<String>,
+	/// Conditions of the suggestion, all of them evaluated to true
+	pub conditions: Vec<String>,
+	/// Placeholders as written in the rule, with their expanded values
+	pub expansions: Vec<(String, String)>,
+	/// Given by modules
+	pub description: Option<String>,
+	pub confidence: Option<f64>,
+	/// Never executed without the user confirming it
+	pub requires_confirmation: bool,
+}
+
+impl Origin {
+	pub fn new(source: Source) -> Self {
+		Origin {
+			source,
+			pattern: None,
+			file: None,
+			conditions: vec![],
+			expansions: vec![],
+			description: None,
+			confidence: None,
+			requires_confirmation: false,
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct Candidate {
+	pub command: String,
+	pub origin: Origin,
+}
+
 pub struct Data {
 	pub shell: String,
 	pub env: Option<String>,
@@ -34,14 +82,21 @@ pub struct Data {
 	pub target_rule: Option<String>,
 	pub suggest: Option<String>,
 	pub candidates: Vec<String>,
+	/// Origins of `candidates`, in the same order
+	pub origins: Vec<Origin>,
 	pub split: Vec<String>,
 	pub comments: Option<String>,
 	pub alias: Option<HashMap<String, String>>,
 	pub privilege: Option<String>,
 	pub error: String,
+	/// Re-running the command timed out, `error` is partial
+	pub timed_out: bool,
 	pub executables: Vec<String>,
 	pub modules: Vec<String>,
 	pub fallbacks: Vec<String>,
+	/// Rules from the rule directories by the executable they are for, read
+	/// when first needed, see `rules::match_user_rules`
+	pub user_rules: Mutex<HashMap<String, RuleSet>>,
 	pub config: Config,
 	pub mode: Mode,
 }
@@ -52,14 +107,7 @@ impl Data {
 		let command = last_command(&shell).trim().to_string();
 		let alias = alias_map(&shell);
 		let mode = run_mode();
-		let (mut executables, modules, fallbacks);
-		let lib_dir = {
-			if let Ok(lib_dir) = std::env::var("_PR_LIB") {
-				Some(lib_dir)
-			} else {
-				option_env!("_DEF_PR_LIB").map(|dir| dir.to_string())
-			}
-		};
+		let lib_dir = lib_dir();
 
 		// let prompt_prefix = std::env::var("_PR_PREFIX").ok();
 		let prompt_prefix = match std::env::var("_PR_PREFIX") {
@@ -83,78 +131,14 @@ impl Data {
 		#[cfg(debug_assertions)]
 		eprintln!("lib_dir: {:?}", lib_dir);
 
-		(executables, modules, fallbacks) = if let Some(lib_dir) = lib_dir {
-			let mut modules = vec![];
-			let mut fallbacks = vec![];
-			let mut executables = get_path_files();
-			if let Some(alias) = &alias {
-				for command in alias.keys() {
-					if executables.contains(command) {
-						continue;
-					}
-					executables.push(command.to_string());
-				}
-			}
-
-			let path = lib_dir.split(path_env_sep()).collect::<Vec<&str>>();
-
-			for p in path {
-				#[cfg(windows)]
-				let p = path_convert(p);
-
-				let files = match std::fs::read_dir(p) {
-					Ok(files) => files,
-					Err(_) => continue,
-				};
-				for file in files {
-					let file = file.unwrap();
-					let file_name = file.file_name().into_string().unwrap();
-					let file_path = file.path();
-
-					if file_name.starts_with("_pay-respects-module-") {
-						modules.push(file_path.to_string_lossy().to_string());
-					} else if file_name.starts_with("_pay-respects-fallback-") {
-						fallbacks.push(file_path.to_string_lossy().to_string());
-					}
-				}
-			}
-
-			modules.sort_unstable();
-			fallbacks.sort_unstable();
-
-			(executables, modules, fallbacks)
-		} else {
-			let path_executables = get_path_files();
-			let mut executables = vec![];
-			let mut modules = vec![];
-			let mut fallbacks = vec![];
-			for exe in path_executables {
-				if exe.starts_with("_pay-respects-module-") {
-					modules.push(exe.to_string());
-				} else if exe.starts_with("_pay-respects-fallback-") {
-					fallbacks.push(exe.to_string());
-				} else {
-					executables.push(exe.to_string());
-				}
-			}
-			modules.sort_unstable();
-			fallbacks.sort_unstable();
-			if let Some(alias) = &alias {
-				for command in alias.keys() {
-					if executables.contains(command) {
-						continue;
-					}
-					executables.push(command.to_string());
-				}
-			}
-
-			(executables, modules, fallbacks)
-		};
+		let config = load_config();
+		let (mut executables, modules, fallbacks) = discover(lib_dir.as_deref(), alias.as_ref());
+		let modules = arrange(&config.modules, modules);
+		let fallbacks = arrange(&config.modules, fallbacks);
 
 		let builtins = builtin_commands(&shell);
 		executables.extend(builtins.clone());
 		executables = executables.iter().unique().cloned().collect();
-		let config = load_config();
 
 		let mut init = Data {
 			shell,
@@ -165,14 +149,17 @@ impl Data {
 			target_rule: None,
 			suggest: None,
 			candidates: vec![],
+			origins: vec![],
 			alias,
 			split: vec![],
 			comments: None,
 			privilege: None,
 			error: "".to_string(),
+			timed_out: false,
 			executables,
 			modules,
 			fallbacks,
+			user_rules: Mutex::new(HashMap::new()),
 			config,
 			mode,
 		};
@@ -319,7 +306,7 @@ impl Data {
 		if let Some(error) = error {
 			self.error = error.split_whitespace().collect::<Vec<&str>>().join(" ");
 		} else {
-			self.error = get_error(&self.shell, &self.command, self);
+			(self.error, self.timed_out) = get_error(&self.shell, &self.command, self);
 		}
 	}
 
@@ -341,6 +328,7 @@ impl Data {
 		};
 	}
 	pub fn update_target_rule(&mut self) {
+		self.target_rule = None;
 		if self.config.merge_commands.is_none() {
 			return;
 		}
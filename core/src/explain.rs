@@ -0,0 +1,54 @@
+use colored::Colorize;
+
+use crate::data::{Origin, Source};
+
+/// Describes why a candidate was suggested: the rule or module that
+/// produced it, the matched pattern, the conditions and the placeholders
+pub fn explanation(origin: &Origin) -> String {
+	let mut lines = vec![];
+
+	let source = match &origin.source {
+		Source::Rule(name) => format!("rule: {}", name),
+		Source::Module(name) => format!("module: {}", name),
+		Source::Fallback(name) => format!("fallback: {}", name),
+	};
+	match &origin.file {
+		Some(file) => lines.push(format!("{} ({})", source, file)),
+		None => lines.push(source),
+	}
+
+	if let Some(description) = &origin.description {
+		lines.push(format!("description: {}", description));
+	}
+	if let Some(confidence) = origin.confidence {
+		lines.push(format!("confidence: {:.2}", confidence));
+	}
+	if origin.requires_confirmation {
+		lines.push("requires confirmation".to_string());
+	}
+	if let Some(pattern) = &origin.pattern {
+		lines.push(format!("pattern: {}", pattern));
+	}
+	for condition in &origin.conditions {
+		lines.push(format!("condition: {}", condition));
+	}
+	for (placeholder, value) in &origin.expansions {
+		lines.push(format!("{} => \"{}\"", shorten(placeholder), value));
+	}
+
+	lines
+		.iter()
+		.map(|line| line.dimmed().to_string())
+		.collect::<Vec<String>>()
+		.join("\n")
+}
+
+/// Placeholders such as `{{typo[1](...)}}` can hold long lists
+fn shorten(placeholder: &str) -> String {
+	const MAX_LEN: usize = 60;
+	if placeholder.chars().count() <= MAX_LEN {
+		return placeholder.to_string();
+	}
+	let start = placeholder.chars().take(MAX_LEN - 5).collect::<String>();
+	format!("{}...}}}}", start)
+}
@@ -3,13 +3,17 @@ use crate::shell::is_privileged;
 use colored::*;
 use pay_respects_utils::evals::split_command;
 
-// to_string() is necessary here, otherwise there won't be color in the output
-#[warn(clippy::unnecessary_to_owned)]
-pub fn highlight_difference(data: &Data, suggested_command: &str, active: bool) -> Option<String> {
-	// let replaced_newline = suggested_command.replace('\n', r" {{newline}} ");
-	let shell = &data.shell;
-	let last_command = &data.command;
-	let mut split_suggested_command = split_command(suggested_command);
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SpanKind {
+	Same,
+	Changed,
+	Comment,
+}
+
+/// Splits the suggested command into tokens, marking those that were already
+/// present in the last command
+pub fn diff_spans(last_command: &str, suggested_command: &str) -> Option<Vec<(String, SpanKind)>> {
+	let split_suggested_command = split_command(suggested_command);
 	let split_last_command = split_command(last_command);
 
 	if split_suggested_command == split_last_command {
@@ -19,44 +23,50 @@ pub fn highlight_difference(data: &Data, suggested_command: &str, active: bool)
 		return None;
 	}
 
-	let privileged = is_privileged(data, &split_suggested_command[0]);
-
-	let mut old_entries = Vec::new();
-	for command in &split_suggested_command {
-		if command.is_empty() {
-			continue;
-		}
-		for old in split_last_command.clone() {
-			if command == &old {
-				old_entries.push(command.clone());
-				break;
-			}
-		}
-	}
-
 	let mut skip = false;
-
-	'next: for entry in split_suggested_command.iter_mut() {
+	let mut spans = Vec::new();
+	for entry in split_suggested_command {
 		if entry == "#" {
 			skip = true;
+			spans.push((entry, SpanKind::Comment));
 			continue;
 		}
 		if entry == "\n" {
 			skip = false;
+			spans.push((entry, SpanKind::Same));
 			continue;
 		}
-		if skip {
-			*entry = entry.normal().to_string();
-			continue;
-		}
-		for old in &old_entries {
-			if old == entry {
-				*entry = color_same(entry, active).to_string();
-				continue 'next;
-			}
-		}
-		*entry = color_diff(entry, active);
+		let kind = if skip {
+			SpanKind::Comment
+		} else if !entry.is_empty() && split_last_command.contains(&entry) {
+			SpanKind::Same
+		} else {
+			SpanKind::Changed
+		};
+		spans.push((entry, kind));
 	}
+	Some(spans)
+}
+
+// to_string() is necessary here, otherwise there won't be color in the output
+#[warn(clippy::unnecessary_to_owned)]
+pub fn highlight_difference(data: &Data, suggested_command: &str, active: bool) -> Option<String> {
+	// let replaced_newline = suggested_command.replace('\n', r" {{newline}} ");
+	let shell = &data.shell;
+	let spans = diff_spans(&data.command, suggested_command)?;
+
+	let privileged = is_privileged(data, &spans[0].0);
+
+	let mut split_suggested_command = spans
+		.into_iter()
+		.map(|(entry, kind)| match kind {
+			SpanKind::Same if entry == "\n" => entry,
+			SpanKind::Same => color_same(&entry, active),
+			SpanKind::Changed => color_diff(&entry, active),
+			SpanKind::Comment if entry == "#" => entry,
+			SpanKind::Comment => entry.normal().to_string(),
+		})
+		.collect::<Vec<String>>();
 
 	if privileged
 		&& (suggested_command.contains("&&")
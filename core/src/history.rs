@@ -0,0 +1,156 @@
+use pay_respects_utils::evals::split_command;
+use pay_respects_utils::files::user_data_dir;
+use pay_respects_utils::strings::{error_fingerprint, print_warning};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Candidate, Data};
+
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+	pub executable: String,
+	pub command: String,
+	pub error: String,
+	pub suggestion: String,
+	pub success: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct History {
+	#[serde(default)]
+	entry: Vec<Entry>,
+}
+
+fn history_file() -> String {
+	format!("{}/history.toml", user_data_dir())
+}
+
+fn enabled(data: &Data) -> bool {
+	data.config.history.enabled && std::env::var("_PR_NO_HISTORY").is_err()
+}
+
+fn load() -> Vec<Entry> {
+	let file = history_file();
+	let content = match std::fs::read_to_string(&file) {
+		Ok(content) => content,
+		Err(_) => return vec![],
+	};
+	match toml::from_str::<History>(&content) {
+		Ok(history) => history.entry,
+		Err(_) => {
+			print_warning(&format!(
+				"Failed to parse history file at {}. Skipping.",
+				file
+			));
+			vec![]
+		}
+	}
+}
+
+/// Records the accepted candidate for the current command, along with
+/// whether it was executed successfully
+pub fn record(data: &Data, candidate: &str, success: bool) {
+	if !enabled(data) || data.config.history.max_entries == 0 {
+		return;
+	}
+
+	let mut entries = load();
+	entries.push(Entry {
+		executable: data.get_executable().to_string(),
+		command: data.command.clone(),
+		error: error_fingerprint(&data.error),
+		suggestion: candidate.to_string(),
+		success,
+	});
+	let max_entries = data.config.history.max_entries;
+	if entries.len() > max_entries {
+		entries.drain(..entries.len() - max_entries);
+	}
+
+	let content = match toml::to_string(&History { entry: entries }) {
+		Ok(content) => content,
+		Err(_) => return,
+	};
+	if std::fs::create_dir_all(user_data_dir()).is_err()
+		|| std::fs::write(history_file(), content).is_err()
+	{
+		print_warning("Failed to write history file");
+	}
+}
+
+/// Moves candidates that were previously accepted for the same executable
+/// and error to the front. Candidates without history keep their order.
+pub fn rank(data: &Data, candidates: &mut [Candidate]) {
+	if !enabled(data) || candidates.len() < 2 {
+		return;
+	}
+
+	let executable = data.get_executable();
+	let entries = load()
+		.into_iter()
+		.filter(|entry| entry.success && entry.executable == executable)
+		.collect::<Vec<Entry>>();
+	if entries.is_empty() {
+		return;
+	}
+
+	let command = &data.command;
+	let error = error_fingerprint(&data.error);
+	let score = |candidate: &Candidate| {
+		let candidate = &candidate.command;
+		let fix = signature(command, candidate);
+		entries
+			.iter()
+			.map(|entry| {
+				if &entry.command == command && &entry.suggestion == candidate {
+					3
+				} else if signature(&entry.command, &entry.suggestion) != fix {
+					0
+				} else if entry.error == error {
+					2
+				} else {
+					1
+				}
+			})
+			.sum::<usize>()
+	};
+
+	candidates.sort_by_cached_key(|candidate| std::cmp::Reverse(score(candidate)));
+}
+
+/// Arguments removed from and added to the command by a suggestion
+fn signature(command: &str, suggestion: &str) -> (Vec<String>, Vec<String>) {
+	let old = split_command(command);
+	let new = split_command(suggestion);
+	let removed = old
+		.iter()
+		.filter(|arg| !new.contains(arg))
+		.cloned()
+		.collect();
+	let added = new
+		.iter()
+		.filter(|arg| !old.contains(arg))
+		.cloned()
+		.collect();
+	(removed, added)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{error_fingerprint, signature};
+
+	#[test]
+	fn test_fingerprint() {
+		assert_eq!(
+			error_fingerprint("error: no such command: `biuld`"),
+			error_fingerprint("error: no such command: `tset`")
+		);
+		assert_eq!(
+			error_fingerprint("cat: /tmp/foo: Permission denied"),
+			"cat: _ permission denied"
+		);
+		assert_eq!(
+			signature("pacman -Syu", "sudo pacman -Syu"),
+			signature("pacman -S vim", "sudo pacman -S vim")
+		);
+	}
+}
@@ -0,0 +1,116 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pay_respects_utils::files::user_cache_dir;
+use serde::{Deserialize, Serialize};
+
+/// Seconds after which a keybinding press starts a new suggestion, even on a
+/// line shown by the last one
+const EXPIRY: u64 = 5;
+
+/// Candidates of the last inline suggestion, to cycle through them when the
+/// keybinding is pressed again
+#[derive(Serialize, Deserialize)]
+struct Cycle {
+	original: String,
+	candidates: Vec<String>,
+	/// Index of the candidate in the buffer, the original line after the last
+	index: usize,
+	/// Seconds since the epoch when the line was shown
+	#[serde(default)]
+	time: u64,
+}
+
+impl Cycle {
+	/// The line shown to the user, candidates with a trailing space to keep
+	/// typing
+	fn shown(&self) -> String {
+		match self.candidates.get(self.index) {
+			Some(candidate) => format!("{} ", candidate),
+			None => self.original.clone(),
+		}
+	}
+
+	/// Moves to the next candidate if `line` is the one shown, then to the
+	/// original line
+	fn advance(&mut self, line: &str, now: u64) -> Option<String> {
+		if self.index >= self.candidates.len()
+			|| line.trim() != self.shown().trim()
+			|| now.saturating_sub(self.time) > EXPIRY
+		{
+			return None;
+		}
+		self.index += 1;
+		Some(self.shown())
+	}
+}
+
+fn cycle_file() -> String {
+	format!("{}/inline.toml", user_cache_dir())
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|time| time.as_secs())
+		.unwrap_or(0)
+}
+
+fn save(cycle: &mut Cycle) {
+	cycle.time = now();
+	let Ok(content) = toml::to_string(cycle) else {
+		return;
+	};
+	// not being able to cycle is not worth a warning
+	let _ = std::fs::create_dir_all(user_cache_dir());
+	let _ = std::fs::write(cycle_file(), content);
+}
+
+/// The next candidate if `line` is one printed by the last inline suggestion,
+/// or the original line after the last candidate
+pub fn next(line: &str) -> Option<String> {
+	let content = std::fs::read_to_string(cycle_file()).ok()?;
+	let mut cycle = toml::from_str::<Cycle>(&content).ok()?;
+	let next = cycle.advance(line, now())?;
+	save(&mut cycle);
+	Some(next)
+}
+
+/// Starts cycling through the candidates of `original`, returning the first
+pub fn start(original: &str, candidates: &[String]) -> String {
+	let mut cycle = Cycle {
+		original: original.to_string(),
+		candidates: candidates.to_vec(),
+		index: 0,
+		time: 0,
+	};
+	save(&mut cycle);
+	cycle.shown()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Cycle;
+
+	#[test]
+	fn test_advance() {
+		let mut cycle = Cycle {
+			original: "gti stauts".to_string(),
+			candidates: vec!["git stauts".to_string(), "git status".to_string()],
+			index: 0,
+			time: 100,
+		};
+		assert_eq!(cycle.advance("gti status", 100), None);
+		// a line shown long ago is a new suggestion
+		assert_eq!(cycle.advance("git stauts", 200), None);
+		assert_eq!(
+			cycle.advance("git stauts ", 101),
+			Some("git status ".to_string())
+		);
+		assert_eq!(
+			cycle.advance("git status", 101),
+			Some("gti stauts".to_string())
+		);
+		// starting over from the original line is a new suggestion
+		assert_eq!(cycle.advance("gti stauts", 101), None);
+	}
+}
@@ -18,12 +18,20 @@ use std::env;
 use sys_locale::get_locale;
 
 mod args;
+mod chain;
+mod check_rules;
 mod config;
 mod data;
+mod explain;
 mod highlighting;
+mod history;
 mod init;
+mod inline;
 mod integrations;
 mod modes;
+mod modules;
+mod protocol;
+mod rerun;
 mod rules;
 mod rules_function;
 mod shell;
@@ -61,6 +69,8 @@ fn main() -> Result<(), std::io::Error> {
 		Suggestion => modes::suggestion(&mut data),
 		Inline => modes::inline(&mut data),
 		Echo => modes::echo(&mut data),
+		Json => modes::json(&mut data),
+		Explain => modes::explain(&mut data),
 		NoConfirm => modes::noconfirm(&mut data),
 		Cnf => modes::cnf(&mut data),
 	}
@@ -1,15 +1,21 @@
 use colored::Colorize;
 use pay_respects_select::select_simple;
 use pay_respects_utils::strings::{format_prefix, print_error, remove_color_codes};
+use serde::Serialize;
 use std::path::Path;
 use std::process::exit;
 
 use pay_respects_utils::evals::best_matches;
 use pay_respects_utils::files::best_match_file;
 
-use crate::data::Data;
-use crate::highlighting::highlight_difference;
-use crate::shell::{add_candidates_no_dup, shell_evaluated_commands};
+use crate::data::{Data, Source};
+use crate::explain;
+use crate::highlighting::{SpanKind, diff_spans, highlight_difference};
+use crate::history;
+use crate::inline;
+use crate::shell::{
+	add_candidates_no_dup, add_privilege, last_command, shell_evaluated_commands, shell_run,
+};
 use crate::suggestions::{inline_suggestion, suggest_candidates};
 use crate::system;
 use crate::{config, suggestions};
@@ -19,14 +25,12 @@ pub fn suggestion(data: &mut Data) {
 
 	loop {
 		last_command = data.command.clone();
-		suggest_candidates(data);
-		if data.candidates.is_empty() {
+		let Some(candidate) = suggestions::suggest_and_select(data) else {
 			break;
 		};
 
-		suggestions::select_candidate(data);
-
 		let execution = suggestions::execute_suggestion(data);
+		history::record(data, &candidate, execution.is_ok());
 		if execution.is_ok() {
 			return;
 		} else {
@@ -47,12 +51,19 @@ pub fn suggestion(data: &mut Data) {
 }
 
 pub fn inline(data: &mut Data) {
+	// the line as typed, before aliases and privileges are handled
+	let line = last_command(&data.shell);
+	if let Some(next) = inline::next(&line) {
+		println!("{}", next);
+		return;
+	}
+
 	inline_suggestion(data);
 
 	if data.candidates.is_empty() {
 		return;
 	}
-	println!("{} ", data.candidates[0]);
+	println!("{}", inline::start(line.trim(), &data.candidates));
 }
 
 pub fn echo(data: &mut Data) {
@@ -63,6 +74,120 @@ pub fn echo(data: &mut Data) {
 	println!("{}", data.candidates.join("<PR_BR>\n"));
 }
 
+/// Prints each candidate followed by why it was suggested
+pub fn explain(data: &mut Data) {
+	suggest_candidates(data);
+	if data.candidates.is_empty() {
+		return;
+	};
+
+	let explained = data
+		.candidates
+		.iter()
+		.zip(&data.origins)
+		.map(|(candidate, origin)| {
+			let highlighted =
+				highlight_difference(data, candidate, true).unwrap_or(candidate.to_string());
+			format!("{}\n{}", highlighted, explain::explanation(origin))
+		})
+		.collect::<Vec<String>>();
+	println!("{}", explained.join("\n\n"));
+}
+
+#[derive(Serialize)]
+struct JsonCandidate<'a> {
+	command: &'a str,
+	source: JsonSource<'a>,
+	pattern: Option<&'a str>,
+	file: Option<&'a str>,
+	conditions: &'a [String],
+	expansions: Vec<JsonExpansion<'a>>,
+	description: Option<&'a str>,
+	confidence: Option<f64>,
+	requires_confirmation: bool,
+	spans: Vec<JsonSpan>,
+	privilege: Option<&'a str>,
+	env: Option<&'a str>,
+	/// Command as it would be executed, with privilege and env applied
+	full_command: String,
+}
+
+#[derive(Serialize)]
+struct JsonSource<'a> {
+	#[serde(rename = "type")]
+	kind: &'a str,
+	name: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonExpansion<'a> {
+	placeholder: &'a str,
+	value: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonSpan {
+	text: String,
+	kind: &'static str,
+}
+
+/// Prints one JSON object per line for each candidate
+pub fn json(data: &mut Data) {
+	suggest_candidates(data);
+
+	for (candidate, origin) in data.candidates.iter().zip(&data.origins) {
+		let (kind, name) = match &origin.source {
+			Source::Rule(name) => ("rule", name),
+			Source::Module(name) => ("module", name),
+			Source::Fallback(name) => ("fallback", name),
+		};
+		let spans = diff_spans(&data.command, candidate)
+			.unwrap_or_default()
+			.into_iter()
+			.map(|(text, kind)| JsonSpan {
+				text,
+				kind: match kind {
+					SpanKind::Same => "same",
+					SpanKind::Changed => "changed",
+					SpanKind::Comment => "comment",
+				},
+			})
+			.collect();
+
+		let mut full_command = candidate.clone();
+		if let Some(env) = &data.env {
+			full_command = format!("{env} {full_command}");
+		}
+		if let Some(privilege) = &data.privilege {
+			full_command = add_privilege(&data.shell, privilege, &full_command);
+		}
+
+		let output = JsonCandidate {
+			command: candidate,
+			source: JsonSource { kind, name },
+			pattern: origin.pattern.as_deref(),
+			file: origin.file.as_deref(),
+			conditions: &origin.conditions,
+			expansions: origin
+				.expansions
+				.iter()
+				.map(|(placeholder, value)| JsonExpansion { placeholder, value })
+				.collect(),
+			description: origin.description.as_deref(),
+			confidence: origin.confidence,
+			requires_confirmation: origin.requires_confirmation,
+			spans,
+			privilege: data.privilege.as_deref(),
+			env: data.env.as_deref(),
+			full_command,
+		};
+		match serde_json::to_string(&output) {
+			Ok(line) => println!("{}", line),
+			Err(err) => print_error(&err.to_string()),
+		}
+	}
+}
+
 pub fn noconfirm(data: &mut Data) {
 	let mut last_command;
 
@@ -73,20 +198,18 @@ pub fn noconfirm(data: &mut Data) {
 			break;
 		};
 
-		let candidate = data.candidates[0].clone();
-		let highlighted = highlight_difference(data, &candidate, true).unwrap();
-
-		let output = if let Some(prefix) = &data.prompt_prefix {
-			data.input_command = remove_color_codes(&highlighted);
-			format_prefix(prefix, &highlighted)
+		// modules can ask for some suggestions to be confirmed
+		let candidate = if data.origins[0].requires_confirmation {
+			let Some(candidate) = suggestions::select_candidate(data) else {
+				break;
+			};
+			candidate
 		} else {
-			candidate.clone()
+			noconfirm_candidate(data)
 		};
-		eprintln!("{}", output);
-		data.update_suggest(&candidate);
-		data.candidates.clear();
 
 		let execution = suggestions::execute_suggestion(data);
+		history::record(data, &candidate, execution.is_ok());
 		if execution.is_ok() {
 			return;
 		} else {
@@ -106,6 +229,24 @@ pub fn noconfirm(data: &mut Data) {
 	);
 }
 
+/// Takes the first candidate as the suggestion
+fn noconfirm_candidate(data: &mut Data) -> String {
+	let candidate = data.candidates[0].clone();
+	let highlighted = highlight_difference(data, &candidate, true).unwrap();
+
+	let output = if let Some(prefix) = &data.prompt_prefix {
+		data.input_command = remove_color_codes(&highlighted);
+		format_prefix(prefix, &highlighted)
+	} else {
+		candidate.clone()
+	};
+	eprintln!("{}", output);
+	data.update_suggest(&candidate);
+	data.candidates.clear();
+	data.origins.clear();
+	candidate
+}
+
 pub fn cnf(data: &mut Data) {
 	let shell = data.shell.clone();
 	let mut split = data.split.clone();
@@ -155,10 +296,13 @@ pub fn cnf(data: &mut Data) {
 
 	add_candidates_no_dup(&data.command, &mut data.candidates, &candidates);
 
-	if !data.candidates.is_empty() {
-		suggestions::select_candidate(data);
-
+	let candidate = match data.candidates.is_empty() {
+		true => None,
+		false => suggestions::select_candidate(data),
+	};
+	if let Some(candidate) = candidate {
 		let status = suggestions::execute_suggestion(data);
+		history::record(data, &candidate, status.is_ok());
 		if status.is_err() {
 			let suggest = data.suggest.clone().unwrap();
 			data.update_command(&suggest);
@@ -232,10 +376,8 @@ pub fn cnf(data: &mut Data) {
 		if install_method == &config::InstallMethod::Shell {
 			// let the shell handle the installation and place the user in a shell
 			// environment with the package installed
-			println!(
-				"{}",
-				system::install_package_shell(data, &package_manager, &package)
-			);
+			let install = system::install_package_shell(data, &package_manager, &package);
+			println!("{}", shell_run(&data.shell, &install));
 			return;
 		}
 
@@ -0,0 +1,385 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use pay_respects_utils::log::dlog;
+
+use std::collections::HashMap;
+
+use pay_respects_utils::files::{get_path_files, path_env_sep};
+
+#[cfg(windows)]
+use pay_respects_utils::files::path_convert;
+
+use crate::config::{ModulesConfig, load_config};
+use crate::data::{Candidate, Data, Origin, Source};
+use crate::shell::module_output;
+
+/// Directories of modules, from `_PR_LIB` or set at compile time
+pub fn lib_dir() -> Option<String> {
+	if let Ok(lib_dir) = std::env::var("_PR_LIB") {
+		Some(lib_dir)
+	} else {
+		option_env!("_DEF_PR_LIB").map(|dir| dir.to_string())
+	}
+}
+
+/// Executables, standard modules and fallback modules, found in the `lib_dir`
+/// or `PATH`
+pub fn discover(
+	lib_dir: Option<&str>,
+	alias: Option<&HashMap<String, String>>,
+) -> (Vec<String>, Vec<String>, Vec<String>) {
+	if let Some(lib_dir) = lib_dir {
+		let mut modules = vec![];
+		let mut fallbacks = vec![];
+		let mut executables = get_path_files();
+		if let Some(alias) = alias {
+			for command in alias.keys() {
+				if executables.contains(command) {
+					continue;
+				}
+				executables.push(command.to_string());
+			}
+		}
+
+		let path = lib_dir.split(path_env_sep()).collect::<Vec<&str>>();
+
+		for p in path {
+			#[cfg(windows)]
+			let p = path_convert(p);
+
+			let files = match std::fs::read_dir(p) {
+				Ok(files) => files,
+				Err(_) => continue,
+			};
+			for file in files {
+				let file = file.unwrap();
+				let file_name = file.file_name().into_string().unwrap();
+				let file_path = file.path();
+
+				if file_name.starts_with("_pay-respects-module-") {
+					modules.push(file_path.to_string_lossy().to_string());
+				} else if file_name.starts_with("_pay-respects-fallback-") {
+					fallbacks.push(file_path.to_string_lossy().to_string());
+				}
+			}
+		}
+
+		(executables, modules, fallbacks)
+	} else {
+		let path_executables = get_path_files();
+		let mut executables = vec![];
+		let mut modules = vec![];
+		let mut fallbacks = vec![];
+		for exe in path_executables {
+			if exe.starts_with("_pay-respects-module-") {
+				modules.push(exe.to_string());
+			} else if exe.starts_with("_pay-respects-fallback-") {
+				fallbacks.push(exe.to_string());
+			} else {
+				executables.push(exe.to_string());
+			}
+		}
+		if let Some(alias) = alias {
+			for command in alias.keys() {
+				if executables.contains(command) {
+					continue;
+				}
+				executables.push(command.to_string());
+			}
+		}
+
+		(executables, modules, fallbacks)
+	}
+}
+
+/// Whether the module is the shipped `runtime-rules` module, whose rules are
+/// evaluated in-process instead, see `rules::match_user_rules`
+fn superseded(module: &str) -> bool {
+	let file_name = std::path::Path::new(module)
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or(module.to_string());
+	file_name.strip_suffix(".exe").unwrap_or(&file_name) == "_pay-respects-module-100-runtime-rules"
+}
+
+/// Priority and name of a module from its file name, e.g. `100` and
+/// `request-ai` for `_pay-respects-fallback-100-request-ai`
+fn split_file_name(module: &str) -> (Option<u32>, String) {
+	let file_name = std::path::Path::new(module)
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or(module.to_string());
+	let name = file_name.strip_suffix(".exe").unwrap_or(&file_name);
+	let name = name
+		.strip_prefix("_pay-respects-module-")
+		.or_else(|| name.strip_prefix("_pay-respects-fallback-"))
+		.unwrap_or(name);
+	match name.split_once('-') {
+		Some((priority, name)) if priority.chars().all(|c| c.is_ascii_digit()) => {
+			(priority.parse().ok(), name.to_string())
+		}
+		_ => (None, name.to_string()),
+	}
+}
+
+/// Name of a module without its prefix and priority, by which it is
+/// configured
+pub fn module_name(module: &str) -> String {
+	split_file_name(module).1
+}
+
+/// Priority set in the config file, or the one in the file name, `100` if
+/// neither
+fn priority(config: &ModulesConfig, module: &str) -> u32 {
+	let (priority, name) = split_file_name(module);
+	config
+		.modules
+		.get(&name)
+		.and_then(|module| module.priority)
+		.or(priority)
+		.unwrap_or(100)
+}
+
+fn enabled(config: &ModulesConfig, module: &str) -> bool {
+	config
+		.modules
+		.get(&module_name(module))
+		.and_then(|module| module.enabled)
+		.unwrap_or(true)
+}
+
+/// Removes the disabled and superseded modules, and sorts the others by
+/// priority
+pub fn arrange(config: &ModulesConfig, mut modules: Vec<String>) -> Vec<String> {
+	modules.retain(|module| enabled(config, module) && !superseded(module));
+	modules.sort_by_cached_key(|module| (priority(config, module), module_name(module)));
+	modules
+}
+
+/// Whether the module is limited to other executables or modes
+pub fn applies(data: &Data, module: &str) -> bool {
+	let Some(config) = data.config.modules.modules.get(&module_name(module)) else {
+		return true;
+	};
+	let executable = data.split.first();
+	config
+		.executables
+		.as_ref()
+		.is_none_or(|executables| executables.iter().any(|exe| Some(exe) == executable))
+		&& config
+			.modes
+			.as_ref()
+			.is_none_or(|modes| modes.iter().any(|mode| mode == data.mode.name()))
+}
+
+/// Prints the modules found and their effective settings, for
+/// `pay-respects modules list`
+pub fn list_modules() {
+	let config = load_config();
+	let (_, modules, fallbacks) = discover(lib_dir().as_deref(), None);
+	if modules.is_empty() && fallbacks.is_empty() {
+		println!("No modules found");
+		return;
+	}
+
+	let mut rows = vec![[
+		"NAME".to_string(),
+		"TYPE".to_string(),
+		"PRIORITY".to_string(),
+		"TIMEOUT".to_string(),
+		"STATUS".to_string(),
+		"PATH".to_string(),
+	]];
+	for (kind, mut modules) in [("module", modules), ("fallback", fallbacks)] {
+		modules
+			.sort_by_cached_key(|module| (priority(&config.modules, module), module_name(module)));
+		for module in modules {
+			let name = module_name(&module);
+			let mut status = match enabled(&config.modules, &module) {
+				_ if superseded(&module) => "superseded by in-process rules".to_string(),
+				true => "enabled".to_string(),
+				false => "disabled".to_string(),
+			};
+			if let Some(module) = config.modules.modules.get(&name) {
+				if let Some(executables) = &module.executables {
+					status.push_str(&format!(", executables: {}", executables.join(" ")));
+				}
+				if let Some(modes) = &module.modes {
+					status.push_str(&format!(", modes: {}", modes.join(" ")));
+				}
+			}
+			rows.push([
+				name.clone(),
+				kind.to_string(),
+				priority(&config.modules, &module).to_string(),
+				format!("{}ms", config.modules.timeout(&name)),
+				status,
+				module,
+			]);
+		}
+	}
+
+	let widths = (0..rows[0].len())
+		.map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap())
+		.collect::<Vec<usize>>();
+	for row in rows {
+		let line = row
+			.iter()
+			.zip(&widths)
+			.map(|(cell, width)| format!("{:width$}", cell, width = width))
+			.collect::<Vec<String>>()
+			.join("  ");
+		println!("{}", line.trim_end());
+	}
+}
+
+/// Runs the modules concurrently, each with its own timeout, and returns their
+/// candidates in the order of the modules. Fallbacks are run one at a time
+/// instead, until one has candidates. Every module is stopped once `cancel` is
+/// set, and `on_finished` is called as soon as a module is done.
+pub fn modules_candidates(
+	data: &Data,
+	modules: &[String],
+	fallback: bool,
+	cancel: &AtomicBool,
+	on_finished: impl Fn(&str, Option<&Vec<Candidate>>) + Sync,
+) -> Vec<Vec<Candidate>> {
+	let modules = modules
+		.iter()
+		.filter(|module| applies(data, module))
+		.collect::<Vec<&String>>();
+	let stop = || cancel.load(Ordering::Relaxed);
+
+	// later fallbacks (e.g. AI) are slow or costly, and only needed when the
+	// earlier ones have nothing
+	if fallback {
+		for module in modules {
+			if stop() {
+				break;
+			}
+			let candidates = module_candidates(data, module, true, &stop);
+			on_finished(module, candidates.as_ref());
+			if let Some(candidates) = candidates {
+				return vec![candidates];
+			}
+		}
+		return vec![];
+	}
+
+	let on_finished = &on_finished;
+	let stop = &stop;
+	thread::scope(|s| {
+		let handles = modules
+			.iter()
+			.map(|module| {
+				s.spawn(move || {
+					let candidates = module_candidates(data, module, false, stop);
+					on_finished(module, candidates.as_ref());
+					candidates
+				})
+			})
+			.collect::<Vec<_>>();
+
+		// a module failing to run doesn't affect the others
+		handles
+			.into_iter()
+			.filter_map(|handle| handle.join().ok().flatten())
+			.collect()
+	})
+}
+
+fn module_candidates(
+	data: &Data,
+	module: &str,
+	fallback: bool,
+	stop: &dyn Fn() -> bool,
+) -> Option<Vec<Candidate>> {
+	let name = module_name(module);
+	let timeout = Duration::from_millis(data.config.modules.timeout(&name));
+	let start = Instant::now();
+	let candidates = module_output(data, module, timeout, stop);
+	dlog(
+		2,
+		&format!("module {} finished in {:?}", name, start.elapsed()),
+	);
+	let candidates = candidates?;
+
+	let name = std::path::Path::new(module)
+		.file_name()
+		.map(|name| name.to_string_lossy().to_string())
+		.unwrap_or(module.to_string());
+	let source = if fallback {
+		Source::Fallback(name)
+	} else {
+		Source::Module(name)
+	};
+	Some(
+		candidates
+			.into_iter()
+			.map(|candidate| {
+				let mut origin = Origin::new(source.clone());
+				origin.description = candidate.description;
+				origin.confidence = candidate.confidence;
+				origin.requires_confirmation = candidate.requires_confirmation;
+				Candidate {
+					command: candidate.command,
+					origin,
+				}
+			})
+			.collect(),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{arrange, module_name};
+	use crate::config::{ModuleConfigReader, ModulesConfig};
+
+	#[test]
+	fn test_module_name() {
+		assert_eq!(
+			module_name("/usr/lib/pay-respects/_pay-respects-fallback-100-request-ai"),
+			"request-ai"
+		);
+		assert_eq!(module_name("_pay-respects-module-50-sudo-ls"), "sudo-ls");
+		assert_eq!(module_name("_pay-respects-module-echo"), "echo");
+	}
+
+	#[test]
+	fn test_arrange() {
+		let mut config = ModulesConfig::default();
+		let module = |enabled, priority| ModuleConfigReader {
+			enabled: Some(enabled),
+			priority,
+			..Default::default()
+		};
+		config
+			.modules
+			.insert("first".to_string(), module(true, Some(1)));
+		config
+			.modules
+			.insert("disabled".to_string(), module(false, None));
+		let modules = [
+			"_pay-respects-module-100-default",
+			"_pay-respects-module-20-disabled",
+			"_pay-respects-module-500-first",
+			"_pay-respects-module-30-second",
+			// rules of the shipped module are evaluated in-process
+			"/usr/bin/_pay-respects-module-100-runtime-rules",
+			"_pay-respects-module-200-runtime-rules",
+		]
+		.map(String::from)
+		.to_vec();
+		assert_eq!(
+			arrange(&config, modules),
+			[
+				"_pay-respects-module-500-first",
+				"_pay-respects-module-30-second",
+				"_pay-respects-module-100-default",
+				"_pay-respects-module-200-runtime-rules",
+			]
+		);
+	}
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use pay_respects_utils::log::dlog;
+
+/// Version of the JSON module protocol, advertised to modules in
+/// `_PR_PROTOCOL`
+pub const VERSION: u32 = 2;
+
+/// Written to the standard input of modules
+#[derive(Serialize)]
+pub struct Request<'a> {
+	pub version: u32,
+	pub command: &'a str,
+	pub argv: &'a [String],
+	pub comments: Option<&'a str>,
+	pub error: &'a str,
+	pub shell: &'a str,
+	pub cwd: String,
+	pub aliases: &'a HashMap<String, String>,
+	pub mode: &'a str,
+	pub executables: &'a [String],
+	/// `settings` of the module in the config file
+	pub settings: &'a toml::Table,
+}
+
+#[derive(Deserialize)]
+struct Response {
+	version: u32,
+	candidates: Vec<ModuleCandidate>,
+}
+
+/// A suggestion of a module
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct ModuleCandidate {
+	pub command: String,
+	/// How likely the suggestion is right, in `[0, 1]`
+	pub confidence: Option<f64>,
+	pub description: Option<String>,
+	/// Never executed without the user confirming it
+	#[serde(default)]
+	pub requires_confirmation: bool,
+}
+
+impl ModuleCandidate {
+	fn legacy(command: &str) -> Self {
+		ModuleCandidate {
+			command: command.trim().to_string(),
+			confidence: None,
+			description: None,
+			requires_confirmation: false,
+		}
+	}
+}
+
+/// Candidates of a module's output, either a response object or, for legacy
+/// modules, suggestions separated by `<_PR_BR>`
+pub fn parse_output(stdout: &str) -> Vec<ModuleCandidate> {
+	let trimmed = stdout.trim();
+	if trimmed.starts_with('{') {
+		match serde_json::from_str::<Response>(trimmed) {
+			Ok(response) if response.version == VERSION => {
+				let mut candidates = response.candidates;
+				// stable, candidates without a confidence are kept last in order
+				candidates.sort_by(|a, b| {
+					let confidence = |c: &ModuleCandidate| c.confidence.unwrap_or(0.0);
+					confidence(b).total_cmp(&confidence(a))
+				});
+				return candidates;
+			}
+			Ok(response) => {
+				dlog(
+					1,
+					&format!("unsupported module protocol version: {}", response.version),
+				);
+				return vec![];
+			}
+			// not a response, but a suggestion starting with `{`
+			Err(_) => {}
+		}
+	}
+	let break_holder = "<_PR_BR>";
+	stdout
+		.trim_end_matches(break_holder)
+		.split(break_holder)
+		.map(ModuleCandidate::legacy)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{ModuleCandidate, parse_output};
+
+	#[test]
+	fn test_parse_output() {
+		let legacy = parse_output("sudo ls<_PR_BR>\ndoas ls<_PR_BR>");
+		assert_eq!(
+			legacy.iter().map(|c| &c.command).collect::<Vec<_>>(),
+			["sudo ls", "doas ls"]
+		);
+
+		let response = r#"{
+			"version": 2,
+			"candidates": [
+				{"command": "git push --force", "confidence": 0.2, "requires_confirmation": true},
+				{"command": "git push", "confidence": 0.9, "description": "typo"}
+			]
+		}"#;
+		assert_eq!(
+			parse_output(response),
+			[
+				ModuleCandidate {
+					command: "git push".to_string(),
+					confidence: Some(0.9),
+					description: Some("typo".to_string()),
+					requires_confirmation: false,
+				},
+				ModuleCandidate {
+					command: "git push --force".to_string(),
+					confidence: Some(0.2),
+					description: None,
+					requires_confirmation: true,
+				},
+			]
+		);
+
+		assert!(parse_output(r#"{"version": 3, "candidates": []}"#).is_empty());
+	}
+}
@@ -0,0 +1,185 @@
+use std::process::{Command, Stdio};
+
+use pay_respects_utils::evals::split_command;
+use pay_respects_utils::lists::{privilege_list, side_effect_commands};
+
+use crate::chain::Chain;
+use crate::config::{RerunConfig, SandboxMode};
+
+#[derive(Debug, PartialEq)]
+pub enum Rerun {
+	Run,
+	Sandbox,
+	Skip,
+}
+
+/// Decides whether the failed command can be re-run to capture its error
+pub fn rerun_policy(command: &str, config: &RerunConfig) -> Rerun {
+	let denied = match Chain::parse(command) {
+		Some(chain) => (0..chain.segments.len()).any(|i| is_denied(chain.segment(i), config)),
+		None => is_denied(command, config),
+	};
+	let sandbox = match config.sandbox {
+		SandboxMode::Never => false,
+		SandboxMode::Denied => denied,
+		SandboxMode::Always => true,
+	};
+	// only probed when the sandbox would be used
+	match (denied, sandbox && sandbox_available()) {
+		(_, true) => Rerun::Sandbox,
+		(true, false) => Rerun::Skip,
+		(false, false) => Rerun::Run,
+	}
+}
+
+fn is_denied(segment: &str, config: &RerunConfig) -> bool {
+	let split = split_command(segment);
+	let privileges = privilege_list();
+	let split = split
+		.iter()
+		.skip_while(|token| {
+			let is_env = token.find('=').is_some_and(|i| i > 0);
+			is_env || privileges.contains(&token.as_str())
+		})
+		.map(|token| token.as_str())
+		.collect::<Vec<&str>>();
+
+	if config.allow.iter().any(|pattern| matches(pattern, &split)) {
+		return false;
+	}
+	config.deny.iter().any(|pattern| matches(pattern, &split))
+		|| side_effect_commands()
+			.iter()
+			.any(|pattern| matches(pattern, &split))
+}
+
+/// The executable of the pattern followed by its arguments, in the same order
+fn matches(pattern: &str, split: &[&str]) -> bool {
+	let pattern = split_command(pattern);
+	let (Some((executable, args)), Some((command, command_args))) =
+		(pattern.split_first(), split.split_first())
+	else {
+		return false;
+	};
+	let command = command.rsplit(std::path::MAIN_SEPARATOR).next().unwrap();
+	if executable != command {
+		return false;
+	}
+	args.is_empty()
+		|| command_args
+			.windows(args.len())
+			.any(|window| window.iter().zip(args).all(|(arg, pattern)| arg == pattern))
+}
+
+const UNSHARE: [&str; 7] = [
+	"--user",
+	"--map-root-user",
+	"--net",
+	"--mount",
+	"--pid",
+	"--fork",
+	"--mount-proc",
+];
+
+#[cfg(target_os = "linux")]
+fn sandbox_available() -> bool {
+	use std::sync::OnceLock;
+
+	static AVAILABLE: OnceLock<bool> = OnceLock::new();
+	*AVAILABLE.get_or_init(|| {
+		Command::new("unshare")
+			.args(UNSHARE)
+			.arg("true")
+			.stdout(Stdio::null())
+			.stderr(Stdio::null())
+			.status()
+			.is_ok_and(|status| status.success())
+	})
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sandbox_available() -> bool {
+	false
+}
+
+/// Wraps the command in new user, mount, PID and network namespaces, with
+/// every mount read-only. The command is not run if any can't be remounted.
+pub fn sandbox(cmd: &Command) -> Command {
+	const SCRIPT: &str = r#"d=$1; shift
+while read -r _ _ _ _ m _; do
+	mount -o remount,bind,ro "$(printf '%b' "$m")" 2>/dev/null || exit 125
+done < /proc/self/mountinfo
+cd "$d" && exec "$@""#;
+
+	let dir = match cmd.get_current_dir() {
+		Some(dir) => dir.to_path_buf(),
+		None => std::env::current_dir().unwrap_or_default(),
+	};
+	let mut sandbox = Command::new("unshare");
+	sandbox
+		.args(UNSHARE)
+		.arg("--")
+		.args(["sh", "-c", SCRIPT, "sh"])
+		.arg(dir)
+		.arg(cmd.get_program())
+		.args(cmd.get_args());
+	for (key, value) in cmd.get_envs() {
+		match value {
+			Some(value) => sandbox.env(key, value),
+			None => sandbox.env_remove(key),
+		};
+	}
+	sandbox
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Rerun, rerun_policy, sandbox, sandbox_available};
+	use crate::config::{RerunConfig, SandboxMode};
+	use std::process::{Command, Stdio};
+
+	#[test]
+	fn test_rerun_policy() {
+		let mut config = RerunConfig {
+			allow: vec![],
+			deny: vec!["make deploy".to_string()],
+			sandbox: SandboxMode::Never,
+		};
+		assert_eq!(rerun_policy("git psuh", &config), Rerun::Run);
+		assert_eq!(rerun_policy("git -C repo push", &config), Rerun::Skip);
+		assert_eq!(rerun_policy("sudo rm -rf build", &config), Rerun::Skip);
+		assert_eq!(
+			rerun_policy("curl -s -X POST localhost", &config),
+			Rerun::Skip
+		);
+		assert_eq!(rerun_policy("curl -s localhost", &config), Rerun::Run);
+		assert_eq!(rerun_policy("make && make deploy", &config), Rerun::Skip);
+
+		config.allow = vec!["rm".to_string()];
+		assert_eq!(rerun_policy("rm dir", &config), Rerun::Run);
+	}
+
+	#[test]
+	fn test_sandbox_read_only() {
+		if !sandbox_available() {
+			return;
+		}
+		let root =
+			std::env::temp_dir().join(format!("pay-respects-sandbox-{}", std::process::id()));
+		let cwd = root.join("cwd");
+		std::fs::create_dir_all(&cwd).unwrap();
+		std::fs::write(root.join("outside"), "").unwrap();
+		std::fs::write(cwd.join("inside"), "").unwrap();
+
+		let mut cmd = Command::new("sh");
+		cmd.args(["-c", "rm ../outside inside; touch new"])
+			.current_dir(&cwd);
+		let status = sandbox(&cmd).stderr(Stdio::null()).status().unwrap();
+
+		assert!(!status.success());
+		assert!(root.join("outside").exists());
+		assert!(cwd.join("inside").exists());
+		assert!(!cwd.join("new").exists());
+		std::fs::remove_dir_all(&root).unwrap();
+	}
+}
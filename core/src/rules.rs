@@ -1,19 +1,94 @@
-use crate::data::Data;
+use crate::data::{Candidate, Data, Origin, Source};
 use pay_respects_parser::{parse_inline_rules, parse_rules};
-use pay_respects_utils::{evals::*, modes::Mode};
+use pay_respects_ruleset::{Condition, Context, Expansions, RuleSet, eval_suggest_explained};
+use pay_respects_utils::{evals::*, files::rule_dirs, modes::Mode};
 
 #[allow(unused)]
 use crate::rules_function::{Functions::*, rules_function};
 
-pub fn match_rule(executable: &str, data: &Data) -> Option<Vec<String>> {
+pub fn match_rule(executable: &str, data: &Data) -> Option<Vec<Candidate>> {
 	use Mode::*;
 	match data.mode {
 		Inline => match_inline(executable, data),
+		_ if data.timed_out => {
+			// the error is partial, also try rules that don't need it
+			let mut candidates = match_pattern(executable, data).unwrap_or_default();
+			candidates.extend(match_inline(executable, data).unwrap_or_default());
+			(!candidates.is_empty()).then_some(candidates)
+		}
 		_ => match_pattern(executable, data),
 	}
 }
 
-fn match_pattern(executable: &str, data: &Data) -> Option<Vec<String>> {
+/// Rules from the rule directories, including those extended to `executable`
+/// and ending with the general rule
+pub fn match_user_rules(executable: &str, data: &Data) -> Option<Vec<Candidate>> {
+	let mut user_rules = data.user_rules.lock().unwrap();
+	let user_rules = user_rules
+		.entry(executable.to_string())
+		.or_insert_with(|| RuleSet::load_for(&rule_dirs(), executable));
+	if user_rules.is_empty() {
+		return None;
+	}
+	let mut context = Context {
+		shell: &data.shell,
+		command: &data.command,
+		error: &data.error,
+		executables: &data.executables,
+		inline: data.mode == Mode::Inline,
+	};
+	let mut matches = user_rules.suggestions(executable, &context);
+	if data.timed_out && !context.inline {
+		context.inline = true;
+		matches.extend(user_rules.suggestions(executable, &context));
+	}
+	let candidates = matches
+		.into_iter()
+		.map(|(rule, suggestion)| {
+			let mut origin = Origin::new(Source::Rule(rule.name.clone()));
+			origin.file = Some(rule.path.clone());
+			origin.pattern = suggestion.pattern;
+			origin.conditions = suggestion.conditions;
+			origin.expansions = suggestion.expansions;
+			Candidate {
+				command: suggestion.command,
+				origin,
+			}
+		})
+		.collect::<Vec<Candidate>>();
+	if candidates.is_empty() {
+		return None;
+	}
+	Some(candidates)
+}
+
+/// Conditions and placeholder values of a generated suggestion
+type Details<'a> = (&'a [&'a str], Expansions);
+
+fn to_candidates(
+	executable: &str,
+	candidates: Vec<String>,
+	matched: &[(&str, &str, &str)],
+	details: Vec<Details>,
+) -> Vec<Candidate> {
+	let mut details = details.into_iter();
+	candidates
+		.into_iter()
+		.enumerate()
+		.map(|(i, command)| {
+			let (rule, file, pattern) = matched.get(i).copied().unwrap_or((executable, "", ""));
+			let (conditions, expansions) = details.next().unwrap_or_default();
+			let mut origin = Origin::new(Source::Rule(rule.to_string()));
+			origin.pattern = (!pattern.is_empty()).then(|| pattern.to_string());
+			origin.file = (!file.is_empty()).then(|| file.to_string());
+			origin.conditions = conditions.iter().map(|x| x.to_string()).collect();
+			origin.expansions = expansions;
+			Candidate { command, origin }
+		})
+		.collect()
+}
+
+fn match_pattern(executable: &str, data: &Data) -> Option<Vec<Candidate>> {
 	// variables to be used by parsed rules
 	let error_msg = &data.error;
 	let error_lower = error_msg
@@ -25,6 +100,8 @@ fn match_pattern(executable: &str, data: &Data) -> Option<Vec<String>> {
 	let last_command = &data.command;
 	let executables = &data.executables;
 	let mut candidates = vec![];
+	let mut matched = vec![];
+	let mut details: Vec<Details> = vec![];
 	let split = split_command(last_command);
 
 	// parse rules into rust code
@@ -33,12 +110,12 @@ fn match_pattern(executable: &str, data: &Data) -> Option<Vec<String>> {
 	if candidates.is_empty() {
 		return None;
 	}
-	Some(candidates)
+	Some(to_candidates(executable, candidates, &matched, details))
 }
 
 #[allow(dead_code)]
 #[allow(unused)]
-fn match_inline(executable: &str, data: &Data) -> Option<Vec<String>> {
+fn match_inline(executable: &str, data: &Data) -> Option<Vec<Candidate>> {
 	// variables to be used by parsed rules
 	// error variables are not used by inlines, they are here for reusing codes
 	let error_msg = &data.error;
@@ -51,6 +128,7 @@ fn match_inline(executable: &str, data: &Data) -> Option<Vec<String>> {
 	let last_command = &data.command;
 	let executables = &data.executables;
 	let mut candidates = vec![];
+	let mut details: Vec<Details> = vec![];
 	let split = split_command(last_command);
 
 	// parse rules into rust code
@@ -59,5 +137,152 @@ fn match_inline(executable: &str, data: &Data) -> Option<Vec<String>> {
 	if candidates.is_empty() {
 		return None;
 	}
-	Some(candidates)
+	Some(to_candidates(executable, candidates, &[], details))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Details, match_rule};
+	use crate::config::Config;
+	use crate::data::Data;
+	use pay_respects_parser::{parse_rule_tests, parse_rules};
+	use pay_respects_ruleset::{Condition, Context, Rule, eval_suggest_explained};
+	use pay_respects_utils::{evals::*, modes::Mode};
+
+	fn test_data(shell: &str, command: &str, error: &str, executables: &[&str]) -> Data {
+		let mut data = Data {
+			shell: shell.to_string(),
+			env: None,
+			prompt_prefix: None,
+			input_command: "".to_string(),
+			command: "".to_string(),
+			target_rule: None,
+			suggest: None,
+			candidates: vec![],
+			origins: vec![],
+			split: vec![],
+			comments: None,
+			alias: None,
+			privilege: None,
+			error: "".to_string(),
+			timed_out: false,
+			executables: executables.iter().map(|x| x.to_string()).collect(),
+			modules: vec![],
+			fallbacks: vec![],
+			user_rules: Default::default(),
+			config: Config::default(),
+			mode: Mode::Suggestion,
+		};
+		data.update_command(command);
+		data.update_error(Some(error.to_string()));
+		data
+	}
+
+	/// Suggestions of the rule file by the interpreter
+	fn interpret(path: &str, data: &Data) -> Vec<String> {
+		let rule = Rule::from_file(path).unwrap();
+		let context = Context {
+			shell: &data.shell,
+			command: &data.command,
+			error: &data.error,
+			executables: &data.executables,
+			inline: false,
+		};
+		rule.suggestions(&context)
+			.into_iter()
+			.map(|suggestion| suggestion.command.trim().to_string())
+			.collect()
+	}
+
+	/// Checks the compiled rule, and that the interpreter suggests the same
+	/// for the rule file
+	fn check_rule(
+		rule: &str,
+		path: &str,
+		command: &str,
+		error: &str,
+		executables: &[&str],
+		shell: &str,
+		expected: &str,
+	) {
+		let data = test_data(shell, command, error, executables);
+		let candidates = match_rule(rule, &data)
+			.unwrap_or_default()
+			.into_iter()
+			.map(|candidate| candidate.command.trim().to_string())
+			.collect::<Vec<String>>();
+		assert!(
+			candidates.iter().any(|candidate| candidate == expected),
+			"`{}` did not suggest `{}`, got {:?}",
+			command,
+			expected,
+			candidates
+		);
+
+		// `FUNCTION` suggestions are only compiled
+		let interpreted = interpret(path, &data);
+		assert!(
+			interpreted.iter().all(|x| candidates.contains(x)),
+			"interpreted `{}` differs from compiled: {:?} and {:?}",
+			path,
+			interpreted,
+			candidates
+		);
+	}
+
+	// `[[test]]` entries of the rule files
+	parse_rule_tests!("rules");
+
+	/// Candidates of the rules in `tests/conformance`, compiled
+	#[allow(unused)]
+	fn match_conformance(data: &Data) -> Vec<String> {
+		let executable = "conformance";
+		let error_msg = &data.error;
+		let error_lower = error_msg
+			.split_whitespace()
+			.collect::<Vec<_>>()
+			.join("")
+			.to_lowercase();
+		let shell = &data.shell;
+		let last_command = &data.command;
+		let executables = &data.executables;
+		let mut candidates = vec![];
+		let mut matched = vec![];
+		let mut details: Vec<Details> = vec![];
+		let split = split_command(last_command);
+
+		parse_rules!("tests/conformance");
+
+		candidates
+			.into_iter()
+			.map(|candidate: String| candidate.trim().to_string())
+			.collect()
+	}
+
+	/// Every placeholder and condition evaluates the same compiled and
+	/// interpreted
+	#[test]
+	fn test_conformance() {
+		let path = concat!(
+			env!("CARGO_MANIFEST_DIR"),
+			"/../tests/conformance/conformance.toml"
+		);
+		for command in [
+			"conformance tow three four",
+			"conformance tow",
+			"conformance thre -m msg",
+			"conformance",
+		] {
+			for error in ["found thing here", "not matched", "nothing"] {
+				let data = test_data("bash", command, error, &["git", "conformance"]);
+				assert_eq!(
+					interpret(path, &data),
+					match_conformance(&data),
+					"`{}` with error `{}`",
+					command,
+					error
+				);
+			}
+		}
+	}
 }
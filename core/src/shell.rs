@@ -1,17 +1,23 @@
 use askama::Template;
 use pay_respects_utils::lists::{alias_skip_expand, blocking_commands, privilege_list};
 use pay_respects_utils::log::dlog;
+use pay_respects_utils::strings::{print_warning, remove_color_codes};
 
-use std::process::{Stdio, exit};
+use std::process::{Child, Stdio, exit};
 
 use std::collections::HashMap;
-use std::sync::mpsc::channel;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use crate::config::CaptureMethod;
 use crate::data::Data;
 use crate::init::Init;
 use crate::integrations::get_error_from_multiplexer;
+use crate::modules::module_name;
+use crate::protocol::{self, ModuleCandidate};
+use crate::rerun::{self, Rerun, rerun_policy};
 use pay_respects_utils::remove_env_var;
 
 /// Run the command without any shell configuration files (noprofile, norc)
@@ -40,6 +46,12 @@ fn clean_shell_command(shell: &str, command: &str) -> std::process::Command {
 		"nu" => {
 			cmd.arg("--no-config-file").arg("-c").arg(command);
 		}
+		"elvish" => {
+			cmd.arg("-norc").arg("-c").arg(command);
+		}
+		"xonsh" => {
+			cmd.arg("--no-rc").arg("-c").arg(command);
+		}
 		_ => {
 			cmd.arg("-c").arg(command);
 		}
@@ -97,11 +109,12 @@ pub fn add_candidates_no_dup(
 	}
 }
 
-pub fn get_error(shell: &str, command: &str, data: &Data) -> String {
+/// Returns the error message, and whether re-running the command timed out
+pub fn get_error(shell: &str, command: &str, data: &Data) -> (String, bool) {
 	let error_msg = std::env::var("_PR_ERROR_MSG");
-	let error = if let Ok(error_msg) = error_msg {
+	let (error, timed_out) = if let Ok(error_msg) = error_msg {
 		remove_env_var!("_PR_ERROR_MSG");
-		return error_msg;
+		return (error_msg, false);
 	} else {
 		let timeout = data.config.timeout;
 		#[cfg(debug_assertions)]
@@ -109,16 +122,16 @@ pub fn get_error(shell: &str, command: &str, data: &Data) -> String {
 
 		let executable = data.get_executable();
 		if executable.is_empty() {
-			return String::new();
+			return (String::new(), false);
 		}
 		if data.executables.contains(&executable.to_string()) {
 			if let Some(unrunnable) = &data.config.blocking_commands
 				&& unrunnable.contains(&executable.to_string())
 			{
-				return String::new();
+				return (String::new(), false);
 			}
 			if blocking_commands().contains(&executable) {
-				return String::new();
+				return (String::new(), false);
 			}
 		}
 		if let Some(error) =
@@ -126,46 +139,227 @@ pub fn get_error(shell: &str, command: &str, data: &Data) -> String {
 		{
 			let message = format!("Captured output from multiplexer: '{}'", error);
 			dlog(5, &message);
-			error
+			(error, false)
 		} else {
-			error_output_threaded(shell, command, timeout)
+			let sandbox = match rerun_policy(command, &data.config.rerun) {
+				Rerun::Run => false,
+				Rerun::Sandbox => true,
+				Rerun::Skip => {
+					dlog(1, "Command has side effects, not re-running it");
+					return (String::new(), false);
+				}
+			};
+			error_output_threaded(
+				shell,
+				command,
+				timeout,
+				&data.config.capture_method,
+				sandbox,
+			)
 		}
 	};
-	error.split_whitespace().collect::<Vec<&str>>().join(" ")
+	let error = error.split_whitespace().collect::<Vec<&str>>().join(" ");
+	(error, timed_out)
 }
 
-pub fn error_output_threaded(shell: &str, command: &str, timeout: u64) -> String {
-	let (sender, receiver) = channel();
-
-	thread::scope(|s| {
-		s.spawn(|| {
-			sender
-				.send(
-					clean_shell_command(shell, command)
-						.env("LC_ALL", "C")
-						.output()
-						.unwrap_or_else(|_| {
-							panic!(
-								"failed to execute process, is '{}' the correct executable?",
-								shell
-							)
-						}),
-				)
-				.expect("failed to send output");
-		});
+pub fn error_output_threaded(
+	shell: &str,
+	command: &str,
+	timeout: u64,
+	capture_method: &CaptureMethod,
+	sandbox: bool,
+) -> (String, bool) {
+	let timeout = Duration::from_millis(timeout);
+	let output = match capture_method {
+		CaptureMethod::Pty => pty_error_output(shell, command, timeout, sandbox),
+		CaptureMethod::Pipe => None,
+	};
+	let (output, timed_out) =
+		output.unwrap_or_else(|| error_output(shell, command, timeout, sandbox));
+	if timed_out {
+		use colored::*;
+		print_warning(&format!(
+			"Timeout while executing command, suggestions are based on partial output: {}",
+			command.red()
+		));
+	}
+	(output, timed_out)
+}
 
-		match receiver.recv_timeout(Duration::from_millis(timeout)) {
-			Ok(output) => match output.stderr.is_empty() {
-				true => String::from_utf8_lossy(&output.stdout).to_string(),
-				false => String::from_utf8_lossy(&output.stderr).to_string(),
-			},
-			Err(_) => {
-				use colored::*;
-				eprintln!("Timeout while executing command: {}", command.red());
-				exit(1);
-			}
+/// Command to re-run the failed command with, in a sandbox if requested
+fn rerun_command(shell: &str, command: &str, sandbox: bool) -> std::process::Command {
+	let mut cmd = clean_shell_command(shell, command);
+	cmd.env("LC_ALL", "C");
+	if sandbox {
+		return rerun::sandbox(&cmd);
+	}
+	cmd
+}
+
+fn error_output(shell: &str, command: &str, timeout: Duration, sandbox: bool) -> (String, bool) {
+	let mut cmd = rerun_command(shell, command, sandbox);
+	cmd.stdin(Stdio::null())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::piped());
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		// so that the children of the shell can be killed as well
+		cmd.process_group(0);
+	}
+	let mut child = cmd.spawn().unwrap_or_else(|_| {
+		panic!(
+			"failed to execute process, is '{}' the correct executable?",
+			shell
+		)
+	});
+	let readers = vec![
+		read_output(child.stdout.take().unwrap()),
+		read_output(child.stderr.take().unwrap()),
+	];
+	let (output, timed_out) = wait_output(child, readers, timeout, &|| false);
+	let output = match output[1].is_empty() {
+		true => &output[0],
+		false => &output[1],
+	};
+	(String::from_utf8_lossy(output).to_string(), timed_out)
+}
+
+type Reader = (thread::JoinHandle<()>, Arc<Mutex<Vec<u8>>>);
+
+/// Reads the output as it comes, to keep what was printed if killed
+fn read_output(mut output: impl Read + Send + 'static) -> Reader {
+	let buffer = Arc::new(Mutex::new(vec![]));
+	let shared = buffer.clone();
+	let handle = thread::spawn(move || {
+		let mut chunk = [0; 4096];
+		// reading a pseudo-terminal fails with EIO once closed
+		while let Ok(n) = output.read(&mut chunk)
+			&& n > 0
+		{
+			shared.lock().unwrap().extend_from_slice(&chunk[..n]);
+		}
+	});
+	(handle, buffer)
+}
+
+/// Waits for the child to exit and its output to be read. The child and its
+/// process group are killed after `timeout` or once `stop` returns true, returning
+/// what was read until then.
+fn wait_output(
+	mut child: Child,
+	readers: Vec<Reader>,
+	timeout: Duration,
+	stop: &dyn Fn() -> bool,
+) -> (Vec<Vec<u8>>, bool) {
+	let start = Instant::now();
+	let mut exited = false;
+	while start.elapsed() < timeout && !stop() {
+		exited = exited || child.try_wait().is_ok_and(|status| status.is_some());
+		if exited && readers.iter().all(|(handle, _)| handle.is_finished()) {
+			break;
 		}
-	})
+		thread::sleep(Duration::from_millis(5));
+	}
+
+	let timed_out = !exited;
+	if timed_out {
+		#[cfg(unix)]
+		unsafe {
+			libc::kill(-(child.id() as i32), libc::SIGKILL);
+		}
+		let _ = child.kill();
+		let _ = child.wait();
+		// output that was already written
+		let start = Instant::now();
+		while start.elapsed() < Duration::from_millis(50)
+			&& !readers.iter().all(|(handle, _)| handle.is_finished())
+		{
+			thread::sleep(Duration::from_millis(5));
+		}
+	}
+	let output = readers
+		.into_iter()
+		.map(|(_, buffer)| std::mem::take(&mut *buffer.lock().unwrap()))
+		.collect();
+	(output, timed_out)
+}
+
+/// Run the command under a pseudo-terminal, so that programs print the same
+/// messages as they did in the user's terminal. Both stdout and stderr are
+/// captured, as they can't be told apart. Returns `None` if no pseudo-terminal
+/// could be opened.
+#[cfg(unix)]
+fn pty_error_output(
+	shell: &str,
+	command: &str,
+	timeout: Duration,
+	sandbox: bool,
+) -> Option<(String, bool)> {
+	use std::os::fd::{FromRawFd, OwnedFd};
+	use std::os::unix::process::CommandExt;
+
+	// same size as the terminal of the user, if any
+	let mut size = libc::winsize {
+		ws_row: 24,
+		ws_col: 80,
+		ws_xpixel: 0,
+		ws_ypixel: 0,
+	};
+	unsafe { libc::ioctl(libc::STDERR_FILENO, libc::TIOCGWINSZ, &mut size) };
+
+	let (mut master, mut slave) = (0, 0);
+	let res = unsafe {
+		libc::openpty(
+			&mut master,
+			&mut slave,
+			std::ptr::null_mut(),
+			std::ptr::null(),
+			&size,
+		)
+	};
+	if res != 0 {
+		dlog(1, "Failed to open a pseudo-terminal, falling back to pipes");
+		return None;
+	}
+	let master = unsafe { OwnedFd::from_raw_fd(master) };
+	let slave = unsafe { OwnedFd::from_raw_fd(slave) };
+
+	let mut cmd = rerun_command(shell, command, sandbox);
+	// as with pipes, nothing to read so that prompts don't wait for input
+	cmd.stdin(Stdio::null())
+		.stdout(Stdio::from(slave.try_clone().ok()?))
+		.stderr(Stdio::from(slave));
+	unsafe {
+		cmd.pre_exec(|| {
+			// become the session leader, with the pseudo-terminal as the
+			// controlling terminal
+			if libc::setsid() == -1
+				|| libc::ioctl(libc::STDOUT_FILENO, libc::TIOCSCTTY as _, 0) == -1
+			{
+				return Err(std::io::Error::last_os_error());
+			}
+			Ok(())
+		});
+	}
+	let child = cmd.spawn().ok()?;
+	// the slave side must be closed on our side to get EOF
+	drop(cmd);
+
+	let readers = vec![read_output(std::fs::File::from(master))];
+	let (output, timed_out) = wait_output(child, readers, timeout, &|| false);
+	let output = remove_color_codes(&String::from_utf8_lossy(&output[0]));
+	Some((output, timed_out))
+}
+
+#[cfg(not(unix))]
+fn pty_error_output(
+	_shell: &str,
+	_command: &str,
+	_timeout: Duration,
+	_sandbox: bool,
+) -> Option<(String, bool)> {
+	None
 }
 
 pub fn command_output(shell: &str, command: &str) -> String {
@@ -206,12 +400,20 @@ pub fn command_output_or_error(shell: &str, command: &str) -> String {
 	}
 }
 
-pub fn module_output(data: &Data, module: &str) -> Option<Vec<String>> {
+/// Candidates of the module, `None` if it printed nothing, timed out or was
+/// stopped
+pub fn module_output(
+	data: &Data,
+	module: &str,
+	timeout: Duration,
+	stop: &dyn Fn() -> bool,
+) -> Option<Vec<ModuleCandidate>> {
 	let shell = &data.shell;
 	let executable = &data.split[0];
 	let mut last_command = data.command.clone();
 	let comments = data.comments.clone();
 	let error_msg = &data.error;
+	// the full list is in the request, environment variables are limited
 	let executables = {
 		let exes = data.executables.clone().join(" ");
 		if exes.len() < 100_000 {
@@ -221,31 +423,111 @@ pub fn module_output(data: &Data, module: &str) -> Option<Vec<String>> {
 		}
 	};
 
-	if let Some(comments) = comments {
+	if let Some(comments) = &comments {
 		last_command = format!("{} # {}", last_command, comments);
 	}
 
-	let output = clean_shell_command(shell, module)
+	// secrets are replaced with placeholders, which are restored in the
+	// suggestions
+	let mut redactor = data.config.redact.redactor();
+	let mut redact = |text: &str| match &mut redactor {
+		Some(redactor) => redactor.redact(text),
+		None => text.to_string(),
+	};
+	let command = redact(&data.command);
+	let argv = data.split.iter().map(|arg| redact(arg)).collect::<Vec<_>>();
+	let comments = comments.as_deref().map(&mut redact);
+	let last_command = redact(&last_command);
+	let error_msg = redact(error_msg);
+	let aliases = data
+		.alias
+		.iter()
+		.flatten()
+		.map(|(name, value)| (name.clone(), redact(value)))
+		.collect::<HashMap<String, String>>();
+
+	let no_settings = toml::Table::new();
+	let settings = data
+		.config
+		.modules
+		.modules
+		.get(&module_name(module))
+		.and_then(|module| module.settings.as_ref())
+		.unwrap_or(&no_settings);
+	let request = protocol::Request {
+		version: protocol::VERSION,
+		command: &command,
+		argv: &argv,
+		comments: comments.as_deref(),
+		error: &error_msg,
+		shell,
+		cwd: std::env::current_dir()
+			.map(|dir| dir.to_string_lossy().to_string())
+			.unwrap_or_default(),
+		aliases: &aliases,
+		mode: data.mode.name(),
+		executables: &data.executables,
+		settings,
+	};
+	let request = serde_json::to_string(&request).unwrap_or_default();
+
+	let mut cmd = clean_shell_command(shell, module);
+	cmd.env("_PR_PROTOCOL", protocol::VERSION.to_string())
+		.env(
+			"_PR_MODULE_SETTINGS",
+			serde_json::to_string(settings).unwrap_or_default(),
+		)
 		.env("_PR_COMMAND", executable)
 		.env("_PR_SHELL", shell)
 		.env("_PR_LAST_COMMAND", last_command)
 		.env("_PR_ERROR_MSG", error_msg)
 		.env("_PR_EXECUTABLES", executables)
-		.stderr(Stdio::inherit())
-		.output()
-		.expect("failed to execute process");
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.stderr(Stdio::inherit());
+	#[cfg(unix)]
+	{
+		use std::os::unix::process::CommandExt;
+		// so that the children of the shell can be killed as well
+		cmd.process_group(0);
+	}
+	let mut child = cmd.spawn().expect("failed to execute process");
 
-	if output.stdout.is_empty() {
+	// legacy modules don't read the request, and may exit before it is written
+	let stdin = child.stdin.take();
+	let writer = thread::spawn(move || {
+		if let Some(mut stdin) = stdin {
+			let _ = stdin.write_all(request.as_bytes());
+		}
+	});
+	let readers = vec![read_output(child.stdout.take().unwrap())];
+	let (output, timed_out) = wait_output(child, readers, timeout, stop);
+	let _ = writer.join();
+
+	if timed_out {
+		if !stop() {
+			print_warning(&format!(
+				"Module {} timed out after {}ms",
+				module_name(module),
+				timeout.as_millis()
+			));
+		}
 		return None;
 	}
-	let break_holder = "<_PR_BR>";
-	Some(
-		String::from_utf8_lossy(&output.stdout)
-			.trim_end_matches(break_holder)
-			.split("<_PR_BR>")
-			.map(|s| s.trim().to_string())
-			.collect::<Vec<String>>(),
-	)
+	if output[0].is_empty() {
+		return None;
+	}
+	let mut candidates = protocol::parse_output(&String::from_utf8_lossy(&output[0]));
+	if let Some(redactor) = &redactor {
+		for candidate in &mut candidates {
+			candidate.command = redactor.restore(&candidate.command);
+			candidate.description = candidate
+				.description
+				.as_deref()
+				.map(|description| redactor.restore(description));
+		}
+	}
+	Some(candidates)
 }
 
 pub fn last_command(shell: &str) -> String {
@@ -303,6 +585,17 @@ pub fn alias_map(shell: &str) -> Option<HashMap<String, String>> {
 				alias_map.insert(alias.to_string(), command.to_string());
 			}
 		}
+		"sh" | "ash" | "dash" | "ksh" | "mksh" => {
+			for line in env.lines() {
+				// some print `alias name=value`, quoting the value or not
+				let line = line.strip_prefix("alias ").unwrap_or(line);
+				let Some((alias, command)) = line.split_once('=') else {
+					continue;
+				};
+				let command = command.trim().trim_matches('\'');
+				alias_map.insert(alias.to_string(), command.to_string());
+			}
+		}
 		"fish" => {
 			for line in env.lines() {
 				let alias = line.replace("alias ", "");
@@ -313,18 +606,26 @@ pub fn alias_map(shell: &str) -> Option<HashMap<String, String>> {
 		}
 		"pwsh" | "powershell" | "ps" => {
 			for line in env.lines() {
-				if !line.starts_with("Alias ") {
+				// `Get-Alias | Out-String` of older init scripts
+				if let Some(line) = line.strip_prefix("Alias ") {
+					let Some((alias, command)) = line.split_once("->") else {
+						continue;
+					};
+					let command = command.split_whitespace().next().unwrap_or("");
+					alias_map.insert(alias.trim().to_string(), command.trim().to_string());
 					continue;
 				}
-				let line = line.replacen("Alias ", "", 1);
-				let (alias, command) = line.split_once("->").unwrap();
-				let command = command.split_whitespace().next().unwrap_or("");
+				let Some((alias, command)) = line.split_once('=') else {
+					continue;
+				};
 				alias_map.insert(alias.trim().to_string(), command.trim().to_string());
 			}
 		}
-		"nu" | _ => {
+		"nu" | "elvish" | "xonsh" | _ => {
 			for line in env.lines() {
-				let (alias, command) = line.split_once('=').unwrap();
+				let Some((alias, command)) = line.split_once('=') else {
+					continue;
+				};
 				alias_map.insert(alias.to_string(), command.to_string());
 			}
 		}
@@ -405,11 +706,34 @@ pub fn initialization(init: &mut Init) {
 		cnf: bool,
 	}
 	#[derive(Template)]
+	#[template(path = "init.sh", escape = "none")]
+	struct ShTemplate<'a> {
+		shell: &'a str,
+		alias: &'a str,
+		binary_path: &'a str,
+	}
+	#[derive(Template)]
+	#[template(path = "init.elv", escape = "none")]
+	struct ElvishTemplate<'a> {
+		shell: &'a str,
+		alias: &'a str,
+		binary_path: &'a str,
+	}
+	#[derive(Template)]
+	#[template(path = "init.xsh", escape = "none")]
+	struct XonshTemplate<'a> {
+		shell: &'a str,
+		alias: &'a str,
+		binary_path: &'a str,
+		cnf: bool,
+	}
+	#[derive(Template)]
 	#[template(path = "init.nu", escape = "none")]
 	struct NuTemplate<'a> {
 		shell: &'a str,
 		alias: &'a str,
 		binary_path: &'a str,
+		cnf: bool,
 	}
 
 	let initialize = match shell.as_str() {
@@ -449,6 +773,30 @@ pub fn initialization(init: &mut Init) {
 			shell,
 			alias,
 			binary_path,
+			cnf,
+		}
+		.render()
+		.unwrap(),
+		// no command not found hook
+		"sh" | "ash" | "dash" | "ksh" | "mksh" => ShTemplate {
+			shell,
+			alias,
+			binary_path,
+		}
+		.render()
+		.unwrap(),
+		"elvish" => ElvishTemplate {
+			shell,
+			alias,
+			binary_path,
+		}
+		.render()
+		.unwrap(),
+		"xonsh" => XonshTemplate {
+			shell,
+			alias,
+			binary_path,
+			cnf,
 		}
 		.render()
 		.unwrap(),
@@ -461,6 +809,42 @@ pub fn initialization(init: &mut Init) {
 	println!("{}", initialize);
 }
 
+/// Escapes a string for a double-quoted JSON or Python string
+fn escape_json(s: &str) -> String {
+	s.replace('\\', "\\\\")
+		.replace('"', "\\\"")
+		.replace('\n', "\\n")
+		.replace('\r', "\\r")
+		.replace('\t', "\\t")
+}
+
+/// Output for `init.nu`, which parses it with `from json`. `run` is a command
+/// run in a new Nushell instance, as there is no `eval`.
+fn nu_eval(command: &str, cd: &str, run: &str) -> String {
+	#[derive(Template)]
+	#[template(path = "eval.nu", escape = "none")]
+	struct NuTemplate<'a> {
+		command: &'a str,
+		cd: &'a str,
+		run: &'a str,
+	}
+
+	let template = NuTemplate {
+		command: &escape_json(command),
+		cd: &escape_json(cd),
+		run: &escape_json(run),
+	};
+	template.render().unwrap()
+}
+
+/// Output for the shell to run the command itself instead of pay-respects
+pub fn shell_run(shell: &str, command: &str) -> String {
+	match shell {
+		"nu" | "nush" | "nushell" => nu_eval("", "", command),
+		_ => command.to_string(),
+	}
+}
+
 pub fn get_shell() -> String {
 	match std::env::var("_PR_SHELL") {
 		Ok(shell) => shell,
@@ -474,26 +858,119 @@ pub fn get_shell() -> String {
 	}
 }
 
-#[allow(unused_variables)]
+/// Builtin commands of the shell, along with those listed by the shell itself
+/// in `_PR_BUILTINS` if set
+#[rustfmt::skip]
 pub fn builtin_commands(shell: &str) -> Vec<String> {
-	// TODO: add the commands for each shell
 	// these should cover most of the builtin commands
 	// (maybe with false positives)
-	let builtin = vec![
-		"alias", "bg", "bind", "break", "builtin", "case", "cd", "command", "compgen", "complete",
-		"continue", "declare", "dirs", "disown", "echo", "enable", "eval", "exec", "exit",
-		"export", "fc", "fg", "getopts", "hash", "help", "history", "if", "jobs", "kill", "let",
-		"local", "logout", "popd", "printf", "pushd", "pwd", "read", "readonly", "return", "set",
-		"shift", "shopt", "source", "suspend", "test", "times", "trap", "type", "typeset",
-		"ulimit", "umask", "unalias", "unset", "until", "wait", "while", "which",
+	let posix = vec![
+		"alias", "bg", "break", "cd", "command", "continue", "echo", "eval", "exec", "exit",
+		"export", "false", "fc", "fg", "getopts", "hash", "jobs", "kill", "local", "printf", "pwd",
+		"read", "readonly", "return", "set", "shift", "test", "times", "trap", "true", "type",
+		"ulimit", "umask", "unalias", "unset", "wait",
 	];
-	builtin.iter().map(|&cmd| cmd.to_string()).collect()
+	let builtin = match shell {
+		"bash" => vec![
+			"alias", "bg", "bind", "break", "builtin", "caller", "case", "cd", "command", "compgen",
+			"complete", "compopt", "continue", "declare", "dirs", "disown", "echo", "enable",
+			"eval", "exec", "exit", "export", "false", "fc", "fg", "getopts", "hash", "help",
+			"history", "if", "jobs", "kill", "let", "local", "logout", "mapfile", "popd", "printf",
+			"pushd", "pwd", "read", "readarray", "readonly", "return", "select", "set", "shift",
+			"shopt", "source", "suspend", "test", "time", "times", "trap", "true", "type",
+			"typeset", "ulimit", "umask", "unalias", "unset", "until", "wait", "while",
+		],
+		"zsh" => vec![
+			"alias", "autoload", "bg", "bindkey", "break", "builtin", "bye", "cd", "chdir",
+			"command", "continue", "declare", "dirs", "disable", "disown", "echo", "emulate",
+			"enable", "eval", "exec", "exit", "export", "false", "fc", "fg", "float", "functions",
+			"getln", "getopts", "hash", "history", "integer", "jobs", "kill", "let", "limit",
+			"local", "logout", "noglob", "popd", "print", "printf", "pushd", "pushln", "pwd",
+			"read", "readonly", "rehash", "return", "sched", "set", "setopt", "shift", "source",
+			"suspend", "test", "times", "trap", "true", "ttyctl", "type", "typeset", "ulimit",
+			"umask", "unalias", "unfunction", "unhash", "unlimit", "unset", "unsetopt", "vared",
+			"wait", "whence", "where", "which", "zcompile", "zformat", "zle", "zmodload",
+			"zparseopts", "zstyle",
+		],
+		"fish" => vec![
+			"abbr", "alias", "and", "argparse", "begin", "bg", "bind", "block", "break",
+			"breakpoint", "builtin", "cd", "command", "commandline", "complete", "contains",
+			"continue", "count", "dirh", "dirs", "disown", "echo", "emit", "eval", "exec", "exit",
+			"false", "fg", "fish_config", "fish_indent", "fish_key_reader", "for", "funced",
+			"funcsave", "function", "functions", "help", "history", "isatty", "jobs", "math",
+			"nextd", "not", "open", "or", "path", "prevd", "printf", "psub", "pwd", "random",
+			"read", "realpath", "return", "set", "set_color", "source", "status", "string",
+			"switch", "test", "time", "true", "type", "ulimit", "umask", "vared", "wait", "while",
+		],
+		"nu" | "nush" | "nushell" => vec![
+			"alias", "all", "any", "append", "cd", "char", "clear", "collect", "compact",
+			"complete", "config", "cp", "date", "decode", "def", "default", "describe", "detect",
+			"do", "drop", "du", "each", "echo", "encode", "enumerate", "error", "every", "exec",
+			"exit", "export", "filter", "find", "first", "flatten", "for", "format", "from", "get",
+			"glob", "group-by", "help", "hide", "history", "http", "if", "ignore", "input",
+			"insert", "into", "is-empty", "items", "job", "join", "keybindings", "kill", "last",
+			"length", "let", "lines", "load-env", "loop", "ls", "match", "math", "merge",
+			"metadata", "mkdir", "module", "move", "mut", "mv", "open", "overlay", "par-each",
+			"parse", "path", "plugin", "prepend", "print", "ps", "random", "range", "reduce",
+			"reject", "rename", "return", "reverse", "rm", "save", "scope", "select", "seq", "skip",
+			"sleep", "sort", "sort-by", "source", "split", "start", "str", "sys", "table", "take",
+			"tee", "to", "touch", "transpose", "try", "uniq", "update", "upsert", "url", "use",
+			"version", "watch", "where", "which", "while", "with-env", "wrap", "zip",
+		],
+		"pwsh" | "powershell" | "ps" => vec![
+			"Add-Content", "Clear-Host", "ConvertFrom-Json", "ConvertTo-Json", "Copy-Item", "exit",
+			"ForEach-Object", "Format-List", "Format-Table", "function", "Get-Alias",
+			"Get-ChildItem", "Get-Command", "Get-Content", "Get-Date", "Get-Help", "Get-History",
+			"Get-Item", "Get-Job", "Get-Location", "Get-Member", "Get-Module", "Get-Process",
+			"Get-Service", "Get-Variable", "Group-Object", "Import-Module", "Invoke-Expression",
+			"Invoke-RestMethod", "Invoke-WebRequest", "Join-Path", "Measure-Object", "Move-Item",
+			"New-Item", "Out-File", "Out-String", "Pop-Location", "Push-Location", "Receive-Job",
+			"Remove-Item", "Rename-Item", "Resolve-Path", "Select-Object", "Select-String",
+			"Set-Content", "Set-Location", "Set-Variable", "Sort-Object", "Split-Path", "Start-Job",
+			"Start-Process", "Start-Sleep", "Stop-Process", "Test-Path", "Wait-Job", "Where-Object",
+			"Write-Error", "Write-Host", "Write-Output",
+		],
+		"ksh" | "mksh" => {
+			let mut ksh = posix;
+			ksh.extend([
+				"autoload", "builtin", "disown", "function", "hist", "integer", "let", "print",
+				"select", "sleep", "typeset", "whence",
+			]);
+			ksh
+		}
+		"elvish" => vec![
+			"all", "and", "break", "cd", "coalesce", "constantly", "continue", "count", "del",
+			"drop", "each", "echo", "eq", "eval", "exact-num", "exit", "external", "fail",
+			"float64", "fn", "for", "from-json", "from-lines", "has-external", "has-key",
+			"has-value", "if", "keys", "kind-of", "nop", "not", "ns", "num", "one", "or", "order",
+			"peach", "pprint", "print", "put", "randint", "range", "read-line", "repeat", "repr",
+			"return", "run-parallel", "search-external", "set", "show", "sleep", "slurp", "src",
+			"styled", "take", "time", "tmp", "to-json", "to-lines", "to-string", "try", "use",
+			"var", "while",
+		],
+		"xonsh" => vec![
+			"bg", "cd", "completer", "dirs", "disown", "exec", "exit", "fg", "history", "jobs",
+			"popd", "pushd", "quit", "showcmd", "source", "source-bash", "source-foreign",
+			"source-zsh", "timeit", "trace", "which", "xonfig", "xontrib", "xpip",
+		],
+		_ => posix,
+	};
+	let mut builtin = builtin
+		.iter()
+		.map(|&cmd| cmd.to_string())
+		.collect::<Vec<String>>();
+	if let Ok(builtins) = std::env::var("_PR_BUILTINS") {
+		builtin.extend(builtins.split_whitespace().map(|cmd| cmd.to_string()));
+		remove_env_var!("_PR_BUILTINS");
+	}
+	builtin
 }
 
 pub fn shell_syntax(shell: &str, command: &str) -> String {
 	#[allow(clippy::single_match)]
 	match shell {
-		"nu" => command.replace("&&\n", ";\n").to_string(),
+		// failing commands stop the rest anyway
+		"nu" | "elvish" => command.replace("&&\n", ";\n").to_string(),
 		_ => command.to_string(),
 	}
 }
@@ -573,14 +1050,33 @@ pub fn shell_evaluated_commands(shell: &str, command: &str, success: bool) {
 		cd: Option<&'a str>,
 	}
 	#[derive(Template)]
-	#[template(path = "eval.nu", escape = "none")]
-	struct NuTemplate<'a> {
+	#[template(path = "eval.ps1", escape = "none")]
+	struct PwshTemplate<'a> {
 		command: &'a str,
-		cd: &'a str,
+		cd: Option<&'a str>,
 	}
 	#[derive(Template)]
-	#[template(path = "eval.ps1", escape = "none")]
-	struct PwshTemplate<'a> {
+	#[template(path = "eval.ksh", escape = "none")]
+	struct KshTemplate<'a> {
+		command: &'a str,
+		cd: Option<&'a str>,
+	}
+	/// No history to add to, the command is kept for `__pr_last` instead
+	#[derive(Template)]
+	#[template(path = "eval.posix.sh", escape = "none")]
+	struct PosixTemplate<'a> {
+		command: &'a str,
+		cd: Option<&'a str>,
+	}
+	#[derive(Template)]
+	#[template(path = "eval.elv", escape = "none")]
+	struct ElvishTemplate<'a> {
+		command: &'a str,
+		cd: Option<&'a str>,
+	}
+	#[derive(Template)]
+	#[template(path = "eval.xsh", escape = "none")]
+	struct XonshTemplate<'a> {
 		command: &'a str,
 		cd: Option<&'a str>,
 	}
@@ -624,32 +1120,56 @@ pub fn shell_evaluated_commands(shell: &str, command: &str, success: bool) {
 			};
 			template.render().unwrap()
 		}
-		"nu" | "nush" | "nushell" => {
-			// JSON-escape the fields so init.nu can parse the output with `from json`
-			let escape_json = |s: &str| {
-				s.replace('\\', "\\\\")
-					.replace('"', "\\\"")
-					.replace('\n', "\\n")
-					.replace('\r', "\\r")
-					.replace('\t', "\\t")
+		"nu" | "nush" | "nushell" => nu_eval(command, cd.as_deref().unwrap_or(""), ""),
+		"pwsh" | "powershell" | "ps" => {
+			// Single-quoted PowerShell string: only ' needs escaping (doubled).
+			let command = command.replace('\'', "''");
+			let template = PwshTemplate {
+				command: &command,
+				cd: cd.as_deref(),
 			};
-			let command = escape_json(command);
-			let cd_escaped = cd.as_deref().map(escape_json).unwrap_or_default();
-			let template = NuTemplate {
+			template.render().unwrap()
+		}
+		"ksh" | "mksh" => {
+			let command = command
+				.replace("\\", "\\\\")
+				.replace("$", "\\$")
+				.replace("`", "\\`")
+				.replace("\"", "\\\"");
+			let template = KshTemplate {
 				command: &command,
-				cd: &cd_escaped,
+				cd: cd.as_deref(),
 			};
 			template.render().unwrap()
 		}
-		"pwsh" | "powershell" | "ps" => {
-			// Single-quoted PowerShell string: only ' needs escaping (doubled).
+		"sh" | "ash" | "dash" => {
+			let command = command
+				.replace("\\", "\\\\")
+				.replace("$", "\\$")
+				.replace("`", "\\`")
+				.replace("\"", "\\\"");
+			let template = PosixTemplate {
+				command: &command,
+				cd: cd.as_deref(),
+			};
+			template.render().unwrap()
+		}
+		"elvish" => {
+			// single-quoted, only ' needs escaping (doubled)
 			let command = command.replace('\'', "''");
-			let template = PwshTemplate {
+			let template = ElvishTemplate {
 				command: &command,
 				cd: cd.as_deref(),
 			};
 			template.render().unwrap()
 		}
+		"xonsh" => {
+			let template = XonshTemplate {
+				command: &escape_json(command),
+				cd: cd.as_deref(),
+			};
+			template.render().unwrap()
+		}
 		_ => {
 			let template = GenericTemplate { cd: cd.as_deref() };
 			template.render().unwrap()
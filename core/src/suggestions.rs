@@ -1,98 +1,147 @@
 use std::io::stderr;
 use std::process::{Stdio, exit};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, mpsc};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use colored::Colorize;
-use pay_respects_select::select;
+use pay_respects_select::{Item, Update, select_explained, select_streamed};
 use pay_respects_utils::log::dlog;
 use pay_respects_utils::strings::{format_prefix, print_error, remove_color_codes};
 
+use crate::chain::Chain;
 use crate::config;
-use crate::data::Data;
+use crate::data::{Candidate, Data};
+use crate::explain;
 use crate::highlighting::highlight_difference;
+use crate::history;
 use crate::integrations::get_error_from_multiplexer;
-use crate::rules::match_rule;
-use crate::shell::{
-	add_candidates_no_dup, add_privilege, module_output, shell_evaluated_commands, shell_syntax,
-};
+use crate::modules::{applies, module_name, modules_candidates};
+use crate::rules::{match_rule, match_user_rules};
+use crate::shell::{add_privilege, shell_evaluated_commands, shell_run, shell_syntax};
 
 pub fn suggest_candidates(data: &mut Data) {
 	if data.split.is_empty() {
 		return;
 	}
-	let shell = &data.shell;
+
+	let mut candidates = match Chain::parse(&data.command) {
+		Some(chain) => chain_candidates(data, &chain, collect_candidates),
+		None => collect_candidates(data),
+	};
+	history::rank(data, &mut candidates);
+
+	if !candidates.is_empty() {
+		set_candidates(data, candidates);
+	}
+}
+
+fn set_candidates(data: &mut Data, candidates: Vec<Candidate>) {
+	(data.candidates, data.origins) = candidates
+		.into_iter()
+		.map(|candidate| {
+			(
+				shell_syntax(&data.shell, &candidate.command),
+				candidate.origin,
+			)
+		})
+		.unzip();
+}
+
+fn collect_candidates(data: &Data) -> Vec<Candidate> {
 	let command = &data.command;
 	let mut final_candidates = vec![];
 
-	let fallbacks = &data.fallbacks;
-
 	if let Some(candidates) = get_standard_suggestions(data) {
-		add_candidates_no_dup(command, &mut final_candidates, &candidates);
-		data.candidates = final_candidates
-			.iter()
-			.map(|s| shell_syntax(shell, s))
-			.collect();
+		add_no_dup(command, &mut final_candidates, candidates);
 	}
 
 	if !final_candidates.is_empty() {
-		return;
+		return final_candidates;
 	}
 
-	for fallback in fallbacks {
-		let candidates = module_output(data, fallback);
-		if let Some(candidates) = candidates {
-			add_candidates_no_dup(command, &mut final_candidates, &candidates);
-			data.candidates = final_candidates
-				.iter()
-				.map(|s| shell_syntax(shell, s))
-				.collect();
-			return;
-		}
+	for candidates in modules_candidates(
+		data,
+		&data.fallbacks,
+		true,
+		&AtomicBool::new(false),
+		|_, _| {},
+	) {
+		add_no_dup(command, &mut final_candidates, candidates);
+	}
+	final_candidates
+}
+
+/// Matches rules against the failing segment of a chained command only, and
+/// rebuilds the full line around each candidate. Falls back to the whole line
+/// if the segment has no suggestion.
+fn chain_candidates(
+	data: &mut Data,
+	chain: &Chain,
+	collect: fn(&Data) -> Vec<Candidate>,
+) -> Vec<Candidate> {
+	let index = chain.failing_segment(&data.error, &data.executables);
+	let segment = chain.segment(index);
+
+	#[cfg(debug_assertions)]
+	eprintln!("chain segment {}: {}", index, segment);
+
+	let command = data.command.clone();
+	let env = data.env.take();
+	let privilege = data.privilege.clone();
+	let comments = data.comments.clone();
+
+	data.update_command(segment);
+	// privilege and environment variables of the segment are kept as is
+	let prefix = segment
+		.strip_suffix(data.command.as_str())
+		.unwrap_or("")
+		.to_string();
+	data.expand_command();
+	let candidates = collect(data);
+
+	data.update_command(&command);
+	data.env = env;
+	data.privilege = privilege;
+	data.comments = comments;
+
+	if candidates.is_empty() {
+		return collect(data);
 	}
+	candidates
+		.into_iter()
+		.map(|candidate| Candidate {
+			command: chain.replace(index, &format!("{}{}", prefix, candidate.command)),
+			origin: candidate.origin,
+		})
+		.collect()
 }
 
-fn get_standard_suggestions(data: &Data) -> Option<Vec<String>> {
+fn get_standard_suggestions(data: &Data) -> Option<Vec<Candidate>> {
 	let command = &data.command;
 	// likely comment only
 	if command.is_empty() {
 		return None;
 	}
-	let target_rule = data.get_target_rule();
-	let privilege = &data.privilege;
-
-	let mut suggest_candidates = vec![];
-	let mut module_candidates = vec![];
+	let mut module_candidates_list = vec![];
 	let mut final_candidates = vec![];
 
 	let modules = &data.modules;
 
 	thread::scope(|s| {
 		s.spawn(|| {
-			for module in modules {
-				let new_candidates = module_output(data, module);
-
-				if let Some(candidates) = new_candidates {
-					add_candidates_no_dup(command, &mut module_candidates, &candidates);
-				}
+			for candidates in
+				modules_candidates(data, modules, false, &AtomicBool::new(false), |_, _| {})
+			{
+				add_no_dup(command, &mut module_candidates_list, candidates);
 			}
 		});
 
-		if let Some(candidates) = match_rule(target_rule, data) {
-			add_candidates_no_dup(command, &mut suggest_candidates, &candidates);
-		}
-		if let Some(candidates) = match_rule("_PR_general", data) {
-			add_candidates_no_dup(command, &mut suggest_candidates, &candidates);
-		}
-		if privilege.is_none()
-			&& let Some(candidates) = match_rule("_PR_privilege", data)
-		{
-			add_candidates_no_dup(command, &mut suggest_candidates, &candidates);
-		}
+		final_candidates = rule_candidates(data);
 	});
 
-	add_candidates_no_dup(command, &mut final_candidates, &module_candidates);
-	add_candidates_no_dup(command, &mut final_candidates, &suggest_candidates);
+	add_no_dup(command, &mut final_candidates, module_candidates_list);
 
 	if !final_candidates.is_empty() {
 		return Some(final_candidates);
@@ -101,13 +150,171 @@ fn get_standard_suggestions(data: &Data) -> Option<Vec<String>> {
 	if std::env::var("_PR_NO_DESPERATE").is_err()
 		&& let Some(candidates) = match_rule("_PR_fallback", data)
 	{
-		add_candidates_no_dup(command, &mut final_candidates, &candidates);
+		add_no_dup(command, &mut final_candidates, candidates);
 		return Some(final_candidates);
 	}
 	None
 }
 
-pub fn select_candidate(data: &mut Data) {
+/// Candidates of the user rules, then of the built-in rules for the command and
+/// the general ones. Those of modules come after, as they are streamed in when
+/// selecting, see `stream_candidates`.
+fn rule_candidates(data: &Data) -> Vec<Candidate> {
+	let command = &data.command;
+	let mut candidates = vec![];
+	if let Some(new_candidates) = match_user_rules(data.get_target_rule(), data) {
+		add_no_dup(command, &mut candidates, new_candidates);
+	}
+	if let Some(new_candidates) = match_rule(data.get_target_rule(), data) {
+		add_no_dup(command, &mut candidates, new_candidates);
+	}
+	if let Some(new_candidates) = match_rule("_PR_general", data) {
+		add_no_dup(command, &mut candidates, new_candidates);
+	}
+	if data.privilege.is_none()
+		&& let Some(new_candidates) = match_rule("_PR_privilege", data)
+	{
+		add_no_dup(command, &mut candidates, new_candidates);
+	}
+	candidates
+}
+
+/// Same as `add_candidates_no_dup`, keeping the origin of each candidate
+fn add_no_dup(command: &str, candidates: &mut Vec<Candidate>, new_candidates: Vec<Candidate>) {
+	#[cfg(debug_assertions)]
+	{
+		eprintln!("Adding candidates for command: '{}'", command);
+		for candidate in &new_candidates {
+			eprintln!(
+				"  - '{}' ({:?})",
+				candidate.command.trim(),
+				candidate.origin
+			);
+		}
+	}
+	for candidate in new_candidates {
+		let trimmed = candidate.command.trim();
+		if trimmed.is_empty() || trimmed == command {
+			continue;
+		}
+		if candidates.iter().any(|c| c.command == trimmed) {
+			continue;
+		}
+		candidates.push(Candidate {
+			command: trimmed.to_string(),
+			origin: candidate.origin,
+		});
+	}
+}
+
+/// Suggests candidates and lets the user select one, returning it as it was
+/// suggested, or `None` if there were no candidates
+pub fn suggest_and_select(data: &mut Data) -> Option<String> {
+	// chained commands are rebuilt from all candidates at once, see
+	// `chain_candidates`
+	let stream = !data.command.is_empty()
+		&& Chain::parse(&data.command).is_none()
+		&& !(data.modules.is_empty() && data.fallbacks.is_empty());
+	if !stream {
+		suggest_candidates(data);
+		if data.candidates.is_empty() {
+			return None;
+		}
+		return select_candidate(data);
+	}
+	stream_candidates(data)
+}
+
+/// Opens the selection with the candidates of rules, and appends those of
+/// modules as they finish. Modules still running once a candidate is selected
+/// are stopped.
+///
+/// Candidates are in the same order as `suggest_candidates`, but those arriving
+/// after the selection is open are only ranked among themselves, and listed
+/// after the ones already shown.
+fn stream_candidates(data: &mut Data) -> Option<String> {
+	let command = data.command.clone();
+	let mut candidates = rule_candidates(data);
+	history::rank(data, &mut candidates);
+
+	let data_ref: &Data = data;
+	let item = |candidate: &Candidate| {
+		let suggestion = shell_syntax(&data_ref.shell, &candidate.command);
+		Item {
+			active: highlight_difference(data_ref, &suggestion, true).unwrap(),
+			inactive: highlight_difference(data_ref, &suggestion, false).unwrap(),
+			details: Some(explain::explanation(&candidate.origin)),
+		}
+	};
+	let items = candidates.iter().map(item).collect::<Vec<Item>>();
+	let shown = Mutex::new(candidates);
+	let cancel = AtomicBool::new(false);
+	let (sender, updates) = mpsc::channel();
+	for module in data.modules.iter().filter(|module| applies(data, module)) {
+		let _ = sender.send(Update::Pending(module_name(module)));
+	}
+
+	let selection = thread::scope(|s| {
+		let (shown, cancel, command) = (&shown, &cancel, &command);
+		s.spawn(move || {
+			let data = data_ref;
+			let append = |mut new_candidates: Vec<Candidate>| {
+				history::rank(data, &mut new_candidates);
+				let mut shown = shown.lock().unwrap();
+				let start = shown.len();
+				add_no_dup(command, &mut shown, new_candidates);
+				let items = shown[start..].iter().map(item).collect::<Vec<Item>>();
+				// sent while locked, so that items are in the same order
+				if !items.is_empty() {
+					let _ = sender.send(Update::Items(items));
+				}
+			};
+			modules_candidates(data, &data.modules, false, cancel, |module, candidates| {
+				if let Some(candidates) = candidates {
+					append(candidates.clone());
+				}
+				let _ = sender.send(Update::Done(module_name(module)));
+			});
+
+			if cancel.load(Ordering::Relaxed) || !shown.lock().unwrap().is_empty() {
+				return;
+			}
+			if std::env::var("_PR_NO_DESPERATE").is_err()
+				&& let Some(candidates) = match_rule("_PR_fallback", data)
+			{
+				append(candidates);
+				return;
+			}
+			for fallback in data.fallbacks.iter().filter(|module| applies(data, module)) {
+				let _ = sender.send(Update::Pending(module_name(fallback)));
+			}
+			let results = modules_candidates(data, &data.fallbacks, true, cancel, |module, _| {
+				let _ = sender.send(Update::Done(module_name(module)));
+			});
+			for candidates in results {
+				append(candidates);
+			}
+		});
+
+		let selection = select_streamed(&|num| prelude(num, true), items, updates);
+		cancel.store(true, Ordering::Relaxed);
+		selection
+	});
+	let selection = selection.unwrap_or_else(|err| {
+		print_error(&format!("Selection failed: {}", err));
+		exit(1);
+	})?;
+
+	let candidate = shown.into_inner().unwrap().swap_remove(selection);
+	let suggestion = shell_syntax(&data.shell, &candidate.command);
+	let selected = highlight_difference(data, &suggestion, true).unwrap();
+	confirm_selection(data, selected, &suggestion);
+	Some(suggestion)
+}
+
+/// Returns the selected candidate as it was suggested, or `None` if there were
+/// no candidates
+pub fn select_candidate(data: &mut Data) -> Option<String> {
 	let candidates = &data.candidates;
 	#[cfg(debug_assertions)]
 	eprintln!("candidates: {candidates:?}");
@@ -122,20 +329,51 @@ pub fn select_candidate(data: &mut Data) {
 		.map(|candidate| highlight_difference(data, candidate, false).unwrap())
 		.collect::<Vec<String>>();
 
-	let msg = format!("{}", t!("multi-suggest", num = candidates.len()))
-		.bold()
-		.blue();
-	let confirm = format!("[{}]", t!("confirm-yes")).green();
-	let hint = format!("{} {} {}", "[↑/↓/j/k]".blue(), confirm, "[ESC]".red());
-	let prelude = format!("{}\n\r{}", msg, hint);
+	// candidates added without an origin, e.g. in `cnf` mode, can't be explained
+	let explanations = (data.origins.len() == candidates.len()).then(|| {
+		data.origins
+			.iter()
+			.map(explain::explanation)
+			.collect::<Vec<String>>()
+	});
 
-	let selection =
-		select(&prelude, &active_candidates, &inactive_candidates).unwrap_or_else(|err| {
-			print_error(&format!("Selection failed: {}", err));
-			exit(1);
-		});
+	let selection = select_explained(
+		&prelude(candidates.len(), explanations.is_some()),
+		&active_candidates,
+		&inactive_candidates,
+		explanations.as_deref(),
+	)
+	.unwrap_or_else(|err| {
+		print_error(&format!("Selection failed: {}", err));
+		exit(1);
+	})?;
 
+	let suggestion = candidates[selection].to_string();
 	let selected = active_candidates[selection].to_string();
+	confirm_selection(data, selected, &suggestion);
+	Some(suggestion)
+}
+
+/// Header of the selection, with the number of candidates and keys
+fn prelude(num: usize, explainable: bool) -> String {
+	let msg = format!("{}", t!("multi-suggest", num = num)).bold().blue();
+	let confirm = format!("[{}]", t!("confirm-yes")).green();
+	let explain = match explainable {
+		true => format!(" {}", format!("[?: {}]", t!("explain")).cyan()),
+		false => String::new(),
+	};
+	let hint = format!(
+		"{} {}{} {}",
+		"[↑/↓/j/k]".blue(),
+		confirm,
+		explain,
+		"[ESC]".red()
+	);
+	format!("{}\n\r{}", msg, hint)
+}
+
+/// Prints the selected candidate, highlighted, and sets it as the suggestion
+fn confirm_selection(data: &mut Data, selected: String, suggestion: &str) {
 	let output = if let Some(prefix) = &data.prompt_prefix {
 		let output = format_prefix(prefix, &selected);
 		data.input_command = remove_color_codes(&output)
@@ -147,11 +385,11 @@ pub fn select_candidate(data: &mut Data) {
 	};
 	eprintln!("{}", output);
 
-	let suggestion = candidates[selection].to_string();
-	data.update_suggest(&suggestion);
+	data.update_suggest(suggestion);
 	data.expand_suggest();
 
 	data.candidates.clear();
+	data.origins.clear();
 }
 
 pub fn execute_suggestion(data: &Data) -> Result<(), String> {
@@ -186,11 +424,14 @@ pub fn inline_suggestion(data: &mut Data) {
 		return;
 	}
 
-	if let Some(candidates) = get_standard_suggestions(data) {
-		data.candidates = candidates
-			.iter()
-			.map(|s| shell_syntax(&data.shell, s))
-			.collect();
+	let standard = |data: &Data| get_standard_suggestions(data).unwrap_or_default();
+	let candidates = match Chain::parse(&data.command) {
+		Some(chain) => chain_candidates(data, &chain, standard),
+		None => standard(data),
+	};
+
+	if !candidates.is_empty() {
+		set_candidates(data, candidates);
 	}
 }
 
@@ -236,7 +477,7 @@ pub fn shell_execution(data: &Data, command: &str) {
 	} else {
 		command
 	};
-	println!("{}", command);
+	println!("{}", shell_run(shell, &command));
 }
 
 fn get_suggestion_error(data: &Data, command: &str) -> Result<(), String> {
@@ -0,0 +1,5 @@
+_PR_LAST_COMMAND="{{ command }}";
+
+{%- if let Some(cd) = self.cd %}
+cd {{ cd }}
+{% endif %}
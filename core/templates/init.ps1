@@ -21,7 +21,8 @@ function __pr_base {
 		$env:_PR_PREFIX = (prompt)
 		$env:_PR_MODE = $mode
 		$env:_PR_LAST_COMMAND = $Command
-		$env:_PR_ALIAS = (Get-Alias | Out-String)
+		$env:_PR_ALIAS = (__pr_alias)
+		$env:_PR_BUILTINS = (Get-Command -CommandType Cmdlet, Function -ListImported).Name -join "`n"
 		$env:_PR_SHELL = "{{ shell }}"
 
 		& '{{ binary_path }}'
@@ -31,10 +32,24 @@ function __pr_base {
 		$env:_PR_MODE = $null;
 		$env:_PR_LAST_COMMAND = $null;
 		$env:_PR_ALIAS = $null;
+		$env:_PR_BUILTINS = $null;
 		$env:_PR_SHELL = $null;
 	}
 }
 
+# aliases and one-line functions as `name=definition`, functions forwarding
+# their arguments are treated as aliases
+function __pr_alias {
+	$aliases = Get-Alias | ForEach-Object { "$($_.Name)=$($_.Definition)" }
+	$functions = Get-ChildItem Function: | ForEach-Object {
+		$definition = $_.Definition.Trim() -replace '\s*[$@]args$', ''
+		if ($definition -and $definition -notmatch '[\r\n;$]' -and -not $definition.StartsWith('__pr_')) {
+			"$($_.Name)=$definition"
+		}
+	}
+	(@($aliases) + @($functions)) -join "`n"
+}
+
 function __pr_inline {
 	$line = $null
 		$cursor = $null
@@ -55,29 +70,20 @@ function __pr_inline {
 }
 
 Set-PSReadLineKeyHandler -Chord Ctrl+x,Ctrl+x -ScriptBlock { __pr_inline }
+{% if cnf %}
+$ExecutionContext.InvokeCommand.CommandNotFoundAction = {
+	param($commandName, $eventArgs)
 
-# Uncomment this block to enable command not found hook
-# It's not very useful as we can't retrieve arguments,
-
-{%- if cnf %}
-# function __pr_invoke {
-# 	try {
-# 		&'{{ binary_path }}' | Invoke-Expression;
-# 	} finally {
-# 		$env:_PR_MODE = $env:null;
-# 		$env:_PR_LAST_COMMAND = $env:null;
-# 		$env:_PR_SHELL = $env:null;
-# 	}
-# }
-
-# $ExecutionContext.InvokeCommand.CommandNotFoundAction = {
-# 	param($commandName, $eventArgs)
-
-# 	$env:_PR_LAST_COMMAND = $commandName -replace '^get-|\.\\','';
-# 	$env:_PR_SHELL = 'pwsh';
-# 	$env:_PR_MODE = 'cnf';
+	# only typed commands, not those looked up by scripts
+	if ($eventArgs.CommandOrigin -ne 'Runspace') {
+		return
+	}
+	$executable = $commandName -replace '^get-|\.\\', ''
+	# only the name is given, PSReadLine records the line before running it
+	$line = try { ([Microsoft.PowerShell.PSConsoleReadLine]::GetHistoryItems() | Select-Object -Last 1).CommandLine.Trim() } catch { '' }
+	$command = if ($line -and ($line -split '\s+')[0] -eq $executable) { $line } else { $executable }
 
-# 	$eventArgs.Command = (Get-Command __pr_invoke);
-# 	$eventArgs.StopSearch = $True;
-# }
+	$eventArgs.CommandScriptBlock = { __pr_base cnf $command | Invoke-Expression }.GetNewClosure()
+	$eventArgs.StopSearch = $True
+}
 {% endif %}
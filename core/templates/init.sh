@@ -0,0 +1,21 @@
+alias {{ alias }}="__pr_main suggest"
+
+__pr_main() {
+	eval "$(__pr_base "$1" "$(__pr_last)")"
+}
+
+# without `fc`, BusyBox ash's history file is read, and otherwise (dash) the
+# last command run through pay-respects, or set in `_PR_LAST_COMMAND`
+__pr_last() {
+	if command -v fc >/dev/null 2>&1; then
+		fc -ln -1 2>/dev/null
+	elif [ "{{ shell }}" = ash ] && [ -r "${HISTFILE:-}" ]; then
+		tail -n 2 "$HISTFILE" | head -n 1
+	else
+		printf '%s\n' "${_PR_LAST_COMMAND:-}"
+	fi
+}
+
+__pr_base() {
+	_PR_MODE="$1" _PR_PREFIX="$PS1" _PR_LAST_COMMAND="$2" _PR_ALIAS="`alias`" _PR_SHELL="{{ shell }}" "{{ binary_path }}"
+}
@@ -0,0 +1,158 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Protocol spoken by the endpoint
+#[derive(Debug, PartialEq)]
+pub enum Backend {
+	/// Streaming chat completions, `data: ` events
+	OpenAi,
+	/// Ollama's `/api/generate`, one JSON object per line
+	Ollama,
+	/// llama.cpp server's `/completion`, `data: ` events
+	LlamaCpp,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletion {
+	// id: String,
+	// object: String,
+	// created: usize,
+	choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Choice {
+	delta: Delta,
+	// index: usize,
+	// finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Delta {
+	content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChunk {
+	response: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LlamaCppChunk {
+	content: Option<String>,
+}
+
+impl Backend {
+	pub fn from_name(name: &str) -> Option<Backend> {
+		match name.to_lowercase().as_str() {
+			"openai" => Some(Backend::OpenAi),
+			"ollama" => Some(Backend::Ollama),
+			"llamacpp" | "llama.cpp" | "llama-cpp" => Some(Backend::LlamaCpp),
+			_ => None,
+		}
+	}
+
+	/// Endpoint used when no URL is given
+	pub fn default_url(&self) -> Option<&'static str> {
+		match self {
+			Backend::OpenAi => None,
+			Backend::Ollama => Some("http://localhost:11434/api/generate"),
+			Backend::LlamaCpp => Some("http://localhost:8080/completion"),
+		}
+	}
+
+	/// Whether the endpoint can be used without an API key
+	pub fn is_local(&self) -> bool {
+		!matches!(self, Backend::OpenAi)
+	}
+
+	/// Fills the rendered request template with the model and prompt
+	pub fn fill_body(&self, body: &mut Value, model: String, prompt: String) {
+		body["model"] = Value::String(model);
+		body["stream"] = Value::Bool(true);
+		match self {
+			Backend::OpenAi => body["messages"][0]["content"] = Value::String(prompt),
+			Backend::Ollama | Backend::LlamaCpp => {
+				if let Some(body) = body.as_object_mut() {
+					body.remove("messages");
+					body.remove("extra_body");
+				}
+				body["prompt"] = Value::String(prompt);
+			}
+		}
+	}
+
+	/// Content of a line of the response stream, if any. Lines that can't
+	/// be parsed are skipped, and errors sent in the stream are returned.
+	pub fn content(&self, line: &str) -> Result<Option<String>, String> {
+		let line = line.trim();
+		let data = match self {
+			Backend::OpenAi | Backend::LlamaCpp => match line.strip_prefix("data:") {
+				Some(data) => data.trim(),
+				None => return Ok(None),
+			},
+			Backend::Ollama => line,
+		};
+		if data.is_empty() || data == "[DONE]" {
+			return Ok(None);
+		}
+		let json = match serde_json::from_str::<Value>(data) {
+			Ok(json) => json,
+			Err(_) => {
+				#[cfg(debug_assertions)]
+				eprintln!("AI module: Skipping malformed chunk: {}", data);
+				return Ok(None);
+			}
+		};
+		let error = &json["error"];
+		if !error.is_null() {
+			let message = error["message"].as_str().or(error.as_str());
+			return Err(message.unwrap_or(data).to_string());
+		}
+
+		let content = match self {
+			Backend::OpenAi => serde_json::from_value::<ChatCompletion>(json)
+				.ok()
+				.and_then(|json| json.choices.into_iter().next())
+				.and_then(|choice| choice.delta.content),
+			Backend::Ollama => serde_json::from_value::<OllamaChunk>(json)
+				.ok()
+				.and_then(|json| json.response),
+			Backend::LlamaCpp => serde_json::from_value::<LlamaCppChunk>(json)
+				.ok()
+				.and_then(|json| json.content),
+		};
+		Ok(content)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Backend;
+
+	#[test]
+	fn test_content() {
+		let openai = r#"data: {"choices":[{"delta":{"content":"git"}}]}"#;
+		assert_eq!(Backend::OpenAi.content(openai), Ok(Some("git".to_string())));
+		assert_eq!(Backend::OpenAi.content("data: [DONE]"), Ok(None));
+		assert_eq!(Backend::OpenAi.content("data: {\"choices\": ["), Ok(None));
+		assert_eq!(Backend::OpenAi.content(": keep-alive"), Ok(None));
+
+		let ollama = r#"{"model":"llama3","response":" push","done":false}"#;
+		assert_eq!(
+			Backend::Ollama.content(ollama),
+			Ok(Some(" push".to_string()))
+		);
+		assert_eq!(
+			Backend::Ollama.content(r#"{"error":"model not found"}"#),
+			Err("model not found".to_string())
+		);
+
+		let llamacpp = r#"data: {"content":"<note>","stop":false}"#;
+		assert_eq!(
+			Backend::LlamaCpp.content(llamacpp),
+			Ok(Some("<note>".to_string()))
+		);
+		assert_eq!(Backend::LlamaCpp.content(""), Ok(None));
+	}
+}
@@ -43,6 +43,11 @@ impl Buffer {
 			state: State::Write,
 		}
 	}
+	/// Whether a line was printed without its new line
+	pub fn pending_line(&self) -> bool {
+		self.state != State::Buf && !self.buf.is_empty()
+	}
+
 	pub fn proc(&mut self, data: &str) {
 		match self.state {
 			State::Write => self.proc_write(data),
@@ -0,0 +1,154 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use pay_respects_utils::files::user_cache_dir;
+use pay_respects_utils::strings::error_fingerprint;
+use serde::{Deserialize, Serialize};
+
+use crate::config::CacheConfig;
+
+/// What a response is cached for
+#[derive(Serialize, Deserialize, PartialEq, Clone)]
+struct Key {
+	command: String,
+	error: String,
+	model: String,
+	url: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+	key: Key,
+	/// Seconds since the Unix epoch
+	created: u64,
+	response: String,
+}
+
+pub struct Cache<'a> {
+	config: &'a CacheConfig,
+	key: Key,
+}
+
+fn cache_dir() -> PathBuf {
+	PathBuf::from(user_cache_dir()).join("ai")
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|time| time.as_secs())
+		.unwrap_or(0)
+}
+
+/// Removes every cached response
+pub fn clear() -> std::io::Result<()> {
+	match std::fs::remove_dir_all(cache_dir()) {
+		Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
+
+impl<'a> Cache<'a> {
+	pub fn new(
+		config: &'a CacheConfig,
+		command: &str,
+		error: &str,
+		model: &str,
+		url: &str,
+	) -> Self {
+		let key = Key {
+			command: command.split_whitespace().collect::<Vec<&str>>().join(" "),
+			error: error_fingerprint(error),
+			model: model.to_string(),
+			url: url.to_string(),
+		};
+		Cache { config, key }
+	}
+
+	fn file(&self) -> PathBuf {
+		let key = serde_json::to_string(&self.key).unwrap_or_default();
+		cache_dir().join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+	}
+
+	/// The cached response, unless disabled, bypassed or expired
+	pub fn get(&self) -> Option<String> {
+		if !self.config.enabled || std::env::var("_PR_AI_NO_CACHE").is_ok() {
+			return None;
+		}
+		let content = std::fs::read_to_string(self.file()).ok()?;
+		let entry = serde_json::from_str::<Entry>(&content).ok()?;
+		// the file name is only a hash
+		if entry.key != self.key || now().saturating_sub(entry.created) > self.config.ttl {
+			return None;
+		}
+		Some(entry.response)
+	}
+
+	/// Stores the response, and removes the expired and oldest entries above
+	/// the limit
+	pub fn set(&self, response: &str) {
+		if !self.config.enabled || self.config.max_entries == 0 {
+			return;
+		}
+		let entry = Entry {
+			key: self.key.clone(),
+			created: now(),
+			response: response.to_string(),
+		};
+		let Ok(content) = serde_json::to_string(&entry) else {
+			return;
+		};
+		if std::fs::create_dir_all(cache_dir()).is_err()
+			|| std::fs::write(self.file(), content).is_err()
+		{
+			#[cfg(debug_assertions)]
+			eprintln!("AI module: Failed to write cache");
+			return;
+		}
+		self.evict();
+	}
+
+	fn evict(&self) {
+		let Ok(entries) = std::fs::read_dir(cache_dir()) else {
+			return;
+		};
+		let mut files = entries
+			.filter_map(|entry| entry.ok())
+			.filter_map(|entry| {
+				let modified = entry.metadata().ok()?.modified().ok()?;
+				Some((modified, entry.path()))
+			})
+			.collect::<Vec<_>>();
+		files.sort_unstable();
+
+		let expired = SystemTime::now()
+			.checked_sub(std::time::Duration::from_secs(self.config.ttl))
+			.unwrap_or(UNIX_EPOCH);
+		let excess = files.len().saturating_sub(self.config.max_entries);
+		for (i, (modified, file)) in files.iter().enumerate() {
+			if i < excess || modified < &expired {
+				let _ = std::fs::remove_file(file);
+			}
+		}
+	}
+}
+
+/// 64-bit FNV-1a, the file names must stay the same across builds, which
+/// `DefaultHasher` does not guarantee
+fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+		(hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::fnv1a;
+
+	#[test]
+	fn test_fnv1a() {
+		assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+		assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+		assert_eq!(fnv1a(b"foobar"), 0x85944171f73967e8);
+	}
+}
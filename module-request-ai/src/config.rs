@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+use pay_respects_utils::files::config_files;
+use pay_respects_utils::redact::{RedactConfigReader, Redactor};
+use pay_respects_utils::{merge, merge_option};
+use serde::Deserialize;
+
+#[derive(Deserialize, Default)]
+struct ConfigReader {
+	ai: Option<AiConfigReader>,
+	redact: Option<RedactConfigReader>,
+}
+
+#[derive(Deserialize, Default)]
+struct AiConfigReader {
+	/// Profile used when `_PR_AI_PROFILE` is not set
+	profile: Option<String>,
+	/// Settings shared by every profile
+	#[serde(flatten)]
+	defaults: Profile,
+	profiles: Option<HashMap<String, Profile>>,
+	context: Option<ContextConfigReader>,
+	cache: Option<CacheConfigReader>,
+}
+
+#[derive(Deserialize, Default)]
+struct CacheConfigReader {
+	enabled: Option<bool>,
+	ttl: Option<u64>,
+	max_entries: Option<usize>,
+}
+
+#[derive(Deserialize, Default)]
+struct ContextConfigReader {
+	directory: Option<bool>,
+	git: Option<bool>,
+	project: Option<bool>,
+	shell: Option<bool>,
+	os: Option<bool>,
+	history: Option<bool>,
+	history_entries: Option<usize>,
+	max_size: Option<usize>,
+}
+
+/// Settings of the `[ai]` section or of one of its profiles
+#[derive(Deserialize, Default, Clone)]
+pub struct Profile {
+	pub backend: Option<String>,
+	pub url: Option<String>,
+	pub model: Option<String>,
+	key: Option<String>,
+	key_file: Option<String>,
+	key_command: Option<String>,
+	/// Seconds before giving up on the request
+	pub timeout: Option<u64>,
+	pub retries: Option<u32>,
+	pub extra: Option<String>,
+	pub extra_body: Option<String>,
+	pub additional_prompt: Option<String>,
+	pub locale: Option<String>,
+}
+
+impl Profile {
+	fn merge(&mut self, reader: Profile) {
+		// the key of a later layer replaces the earlier one, whatever its source
+		if reader.key.is_some() || reader.key_file.is_some() || reader.key_command.is_some() {
+			self.key = None;
+			self.key_file = None;
+			self.key_command = None;
+		}
+		merge_option!(
+			self,
+			reader,
+			backend,
+			url,
+			model,
+			key,
+			key_file,
+			key_command,
+			timeout,
+			retries,
+			extra,
+			extra_body,
+			additional_prompt,
+			locale
+		);
+	}
+
+	/// The API key, read from a file or the output of a command if needed
+	pub fn key(&self) -> Result<Option<String>, String> {
+		if let Some(key) = &self.key {
+			return Ok(Some(key.clone()));
+		}
+		if let Some(file) = &self.key_file {
+			let file = expand_home(file);
+			return match std::fs::read_to_string(&file) {
+				Ok(key) => Ok(Some(key.trim().to_string())),
+				Err(e) => Err(format!("Failed to read key file {}: {}", file, e)),
+			};
+		}
+		if let Some(command) = &self.key_command {
+			#[cfg(windows)]
+			let output = Command::new("cmd").args(["/C", command]).output();
+			#[cfg(not(windows))]
+			let output = Command::new("sh").args(["-c", command]).output();
+			return match output {
+				Ok(output) if output.status.success() => Ok(Some(
+					String::from_utf8_lossy(&output.stdout).trim().to_string(),
+				)),
+				Ok(output) => Err(format!(
+					"Key command `{}` failed: {}",
+					command,
+					String::from_utf8_lossy(&output.stderr).trim()
+				)),
+				Err(e) => Err(format!("Failed to run key command `{}`: {}", command, e)),
+			};
+		}
+		Ok(None)
+	}
+}
+
+/// Context providers included in the prompt, all disabled by default
+pub struct ContextConfig {
+	pub directory: bool,
+	pub git: bool,
+	pub project: bool,
+	pub shell: bool,
+	pub os: bool,
+	pub history: bool,
+	pub history_entries: usize,
+	/// Characters kept of each provider
+	pub max_size: usize,
+}
+
+impl Default for ContextConfig {
+	fn default() -> Self {
+		Self {
+			directory: false,
+			git: false,
+			project: false,
+			shell: false,
+			os: false,
+			history: false,
+			history_entries: 5,
+			max_size: 1000,
+		}
+	}
+}
+
+impl ContextConfig {
+	/// Enables only the providers listed in `_PR_AI_CONTEXT`, comma separated
+	fn enable_only(&mut self, providers: &str) -> Result<(), String> {
+		let providers = providers
+			.split(',')
+			.map(|provider| provider.trim())
+			.filter(|provider| !provider.is_empty())
+			.collect::<Vec<&str>>();
+		for provider in &providers {
+			if !["directory", "git", "project", "shell", "os", "history"].contains(provider) {
+				return Err(format!("Unknown context provider: {}", provider));
+			}
+		}
+		let enabled = |name| providers.contains(&name);
+		self.directory = enabled("directory");
+		self.git = enabled("git");
+		self.project = enabled("project");
+		self.shell = enabled("shell");
+		self.os = enabled("os");
+		self.history = enabled("history");
+		Ok(())
+	}
+}
+
+/// Responses cached on disk, see `cache`
+pub struct CacheConfig {
+	pub enabled: bool,
+	/// Seconds a response is used for
+	pub ttl: u64,
+	pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+	fn default() -> Self {
+		Self {
+			enabled: true,
+			ttl: 7 * 24 * 60 * 60,
+			max_entries: 200,
+		}
+	}
+}
+
+/// Settings read from the config files and environment variables
+pub struct Settings {
+	pub profile: Profile,
+	pub context: ContextConfig,
+	pub cache: CacheConfig,
+	/// Redactor of the context, if enabled
+	pub redactor: Option<Redactor>,
+}
+
+#[derive(Default)]
+struct AiConfig {
+	profile: Option<String>,
+	defaults: Profile,
+	profiles: HashMap<String, Profile>,
+	context: ContextConfig,
+	cache: CacheConfig,
+}
+
+impl AiConfig {
+	fn merge(&mut self, reader: AiConfigReader) {
+		merge_option!(self, reader, profile);
+		self.defaults.merge(reader.defaults);
+		for (name, profile) in reader.profiles.unwrap_or_default() {
+			self.profiles.entry(name).or_default().merge(profile);
+		}
+		if let Some(reader) = reader.context {
+			let context = &mut self.context;
+			merge!(
+				context,
+				reader,
+				directory,
+				git,
+				project,
+				shell,
+				os,
+				history,
+				history_entries,
+				max_size
+			);
+		}
+		if let Some(reader) = reader.cache {
+			let cache = &mut self.cache;
+			merge!(cache, reader, enabled, ttl, max_entries);
+		}
+	}
+
+	/// Shared settings overridden by those of the selected profile
+	fn resolve(&self, name: Option<String>) -> Result<Profile, String> {
+		let mut resolved = self.defaults.clone();
+		if let Some(name) = name.or(self.profile.clone()) {
+			match self.profiles.get(&name) {
+				Some(profile) => resolved.merge(profile.clone()),
+				None => return Err(format!("Unknown profile: {}", name)),
+			}
+		}
+		Ok(resolved)
+	}
+}
+
+fn expand_home(path: &str) -> String {
+	match path.strip_prefix("~/") {
+		Some(rest) => match std::env::var("HOME") {
+			Ok(home) => format!("{}/{}", home, rest),
+			Err(_) => path.to_string(),
+		},
+		None => path.to_string(),
+	}
+}
+
+/// Settings of the profile selected by `_PR_AI_PROFILE` or the config files,
+/// along with those shared by every profile
+pub fn load_config() -> Result<Settings, String> {
+	let mut config = AiConfig::default();
+	let (mut redact, mut patterns) = (true, vec![]);
+	if std::env::var("_PR_NO_CONFIG").is_err() {
+		for file in config_files() {
+			let Ok(content) = std::fs::read_to_string(&file) else {
+				continue;
+			};
+			match toml::from_str::<ConfigReader>(&content) {
+				Ok(reader) => {
+					if let Some(reader) = reader.ai {
+						config.merge(reader);
+					}
+					if let Some(reader) = reader.redact {
+						redact = reader.enabled.unwrap_or(redact);
+						patterns = reader.patterns.unwrap_or(patterns);
+					}
+				}
+				Err(e) => eprintln!("AI module: Failed to parse config file at {}: {}", file, e),
+			}
+		}
+	}
+	let profile = config.resolve(std::env::var("_PR_AI_PROFILE").ok())?;
+	let mut context = config.context;
+	if let Ok(providers) = std::env::var("_PR_AI_CONTEXT") {
+		context.enable_only(&providers)?;
+	}
+	// invalid patterns are already reported by pay-respects
+	patterns.retain(|pattern| Redactor::new(std::slice::from_ref(pattern)).is_ok());
+	let redactor = redact.then(|| Redactor::new(&patterns).unwrap());
+	Ok(Settings {
+		profile,
+		context,
+		cache: config.cache,
+		redactor,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{AiConfig, ConfigReader};
+
+	#[test]
+	fn test_resolve() {
+		let system = r#"
+			[ai]
+			profile = "hosted"
+			model = "small"
+			key = "system"
+			[ai.profiles.hosted]
+			url = "https://example.com"
+		"#;
+		let user = r#"
+			[ai]
+			timeout = 10
+			[ai.profiles.local]
+			backend = "ollama"
+			key_command = "echo local"
+			timeout = 60
+		"#;
+		let load = || {
+			let mut config = AiConfig::default();
+			for content in [system, user] {
+				let reader: ConfigReader = toml::from_str(content).unwrap();
+				config.merge(reader.ai.unwrap());
+			}
+			config
+		};
+
+		let hosted = load().resolve(None).unwrap();
+		assert_eq!(hosted.url.as_deref(), Some("https://example.com"));
+		assert_eq!(hosted.model.as_deref(), Some("small"));
+		assert_eq!(hosted.timeout, Some(10));
+		assert_eq!(hosted.key().unwrap().as_deref(), Some("system"));
+
+		let local = load().resolve(Some("local".to_string())).unwrap();
+		assert_eq!(local.backend.as_deref(), Some("ollama"));
+		assert_eq!(local.url, None);
+		assert_eq!(local.timeout, Some(60));
+		assert_eq!(local.key, None);
+
+		assert!(load().resolve(Some("missing".to_string())).is_err());
+	}
+}
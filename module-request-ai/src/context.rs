@@ -0,0 +1,183 @@
+use std::process::{Command, Stdio};
+
+use pay_respects_utils::redact::Redactor;
+
+use crate::config::ContextConfig;
+
+/// Manifest files and the project type they indicate
+#[rustfmt::skip]
+const MANIFESTS: &[(&str, &str)] = &[
+	("Cargo.toml", "Rust (Cargo)"),
+	("package.json", "JavaScript/TypeScript (npm)"),
+	("deno.json", "JavaScript/TypeScript (Deno)"),
+	("pyproject.toml", "Python"),
+	("requirements.txt", "Python"),
+	("setup.py", "Python"),
+	("go.mod", "Go"),
+	("pom.xml", "Java (Maven)"),
+	("build.gradle", "Java/Kotlin (Gradle)"),
+	("build.gradle.kts", "Kotlin (Gradle)"),
+	("Gemfile", "Ruby (Bundler)"),
+	("composer.json", "PHP (Composer)"),
+	("mix.exs", "Elixir (Mix)"),
+	("pubspec.yaml", "Dart/Flutter"),
+	("Package.swift", "Swift"),
+	("stack.yaml", "Haskell (Stack)"),
+	("dune-project", "OCaml (Dune)"),
+	("build.zig", "Zig"),
+	("CMakeLists.txt", "C/C++ (CMake)"),
+	("meson.build", "Meson"),
+	("Makefile", "Make"),
+	("flake.nix", "Nix flake"),
+	("default.nix", "Nix"),
+	("Dockerfile", "Docker"),
+];
+
+/// Context of the user's environment for the prompt, one section per
+/// enabled provider
+pub fn collect(config: &ContextConfig, shell: &str, redactor: Option<&Redactor>) -> String {
+	let mut sections = vec![];
+	let mut add = |title: &str, content: Option<String>| {
+		let Some(content) = content else {
+			return;
+		};
+		let content = match redactor {
+			Some(redactor) => redactor.mask(content.trim()),
+			None => content.trim().to_string(),
+		};
+		let content = truncate(content, config.max_size);
+		if !content.is_empty() {
+			sections.push(format!("{}:\n{}", title, content));
+		}
+	};
+
+	if config.shell {
+		add("Shell", Some(shell.to_string()));
+	}
+	if config.os {
+		add("Operating system", os_release());
+	}
+	if config.directory {
+		add("Files in the current directory", directory_listing());
+	}
+	if config.project {
+		add("Project type", project_type());
+	}
+	if config.git {
+		add("Git status", git_status());
+	}
+	if config.history {
+		add("Recent commands", history(shell, config.history_entries));
+	}
+	sections.join("\n\n")
+}
+
+fn truncate(content: String, max_size: usize) -> String {
+	if content.chars().count() <= max_size {
+		return content;
+	}
+	let mut content = content.chars().take(max_size).collect::<String>();
+	content.push_str("\n...");
+	content
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+	let output = Command::new(program)
+		.args(args)
+		.stdin(Stdio::null())
+		.stderr(Stdio::null())
+		.output()
+		.ok()?;
+	if !output.status.success() {
+		return None;
+	}
+	Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn os_release() -> Option<String> {
+	#[cfg(target_os = "linux")]
+	if let Ok(release) = std::fs::read_to_string("/etc/os-release") {
+		let name = release
+			.lines()
+			.find_map(|line| line.strip_prefix("PRETTY_NAME="))
+			.map(|name| name.trim_matches('"').to_string());
+		if name.is_some() {
+			return name;
+		}
+	}
+	Some(format!(
+		"{} ({})",
+		std::env::consts::OS,
+		std::env::consts::ARCH
+	))
+}
+
+fn directory_listing() -> Option<String> {
+	let mut entries = std::fs::read_dir(".")
+		.ok()?
+		.filter_map(|entry| entry.ok())
+		.map(|entry| {
+			let name = entry.file_name().to_string_lossy().to_string();
+			match entry.file_type() {
+				Ok(file_type) if file_type.is_dir() => format!("{}/", name),
+				_ => name,
+			}
+		})
+		.collect::<Vec<String>>();
+	entries.sort_unstable();
+	Some(entries.join("\n"))
+}
+
+/// Detected from the manifest files of the nearest directory having any
+fn project_type() -> Option<String> {
+	let dir = std::env::current_dir().ok()?;
+	for dir in dir.ancestors() {
+		let found = MANIFESTS
+			.iter()
+			.filter(|(file, _)| dir.join(file).exists())
+			.map(|(file, project)| format!("{} ({})", project, file))
+			.collect::<Vec<String>>();
+		if !found.is_empty() {
+			return Some(found.join("\n"));
+		}
+	}
+	None
+}
+
+fn git_status() -> Option<String> {
+	command_output("git", &["status", "--short", "--branch"])
+}
+
+/// Last entries of the shell's history file
+fn history(shell: &str, entries: usize) -> Option<String> {
+	let home = std::env::var("HOME").ok()?;
+	let data_home =
+		std::env::var("XDG_DATA_HOME").unwrap_or_else(|_| format!("{}/.local/share", home));
+	let (file, parse): (String, fn(&str) -> Option<&str>) = match shell {
+		"bash" => (
+			std::env::var("HISTFILE").unwrap_or(format!("{}/.bash_history", home)),
+			|line| (!line.starts_with('#')).then_some(line),
+		),
+		"zsh" => (
+			std::env::var("HISTFILE").unwrap_or(format!("{}/.zsh_history", home)),
+			// extended history: `: <start>:<duration>;<command>`
+			|line| match line.strip_prefix(':') {
+				Some(line) => line.split_once(';').map(|(_, command)| command),
+				None => Some(line),
+			},
+		),
+		"fish" => (format!("{}/fish/fish_history", data_home), |line| {
+			line.strip_prefix("- cmd: ")
+		}),
+		_ => return None,
+	};
+	let content = std::fs::read(&file).ok()?;
+	let content = String::from_utf8_lossy(&content);
+	let commands = content
+		.lines()
+		.filter_map(parse)
+		.filter(|command| !command.trim().is_empty())
+		.collect::<Vec<&str>>();
+	let start = commands.len().saturating_sub(entries);
+	Some(commands[start..].join("\n"))
+}
@@ -18,7 +18,11 @@ use std::env;
 
 use crate::requests::ai_suggestion;
 use sys_locale::get_locale;
+mod backends;
 mod buffer;
+mod cache;
+mod config;
+mod context;
 mod requests;
 
 #[macro_use]
@@ -27,6 +31,9 @@ i18n!("i18n", fallback = "en", minify_key = true);
 
 #[tokio::main]
 async fn main() -> Result<(), std::io::Error> {
+	if env::args().nth(1).as_deref() == Some("--clear-cache") {
+		return cache::clear();
+	}
 	if std::env::var("_PR_AI_DISABLE").is_ok() {
 		return Ok(());
 	}
@@ -1,15 +1,38 @@
 use askama::Template;
 use serde_json::Value;
 
+use std::time::Duration;
+
 use futures_util::StreamExt;
-use serde::Deserialize;
+use pay_respects_utils::redact::Redactor;
 
+use crate::backends::Backend;
 use crate::buffer;
+use crate::cache::Cache;
+use crate::config::{CacheConfig, ContextConfig, load_config};
+use crate::context;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Maximum time without receiving anything, models can take a while to start
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
 
 struct Conf {
 	key: String,
 	url: String,
 	model: String,
+	backend: Backend,
+	/// Seconds before giving up on the request
+	timeout: Option<u64>,
+	/// Retries after connection errors, rate limits and server errors
+	retries: u32,
+	extra: Option<String>,
+	extra_body: Option<String>,
+	additional_prompt: Option<String>,
+	locale: Option<String>,
+	context: ContextConfig,
+	redactor: Option<Redactor>,
+	cache: CacheConfig,
 }
 
 // #[derive(Serialize)]
@@ -26,26 +49,6 @@ struct Conf {
 // 	extra_body: Option<Value>,
 // }
 
-#[derive(Debug, Deserialize)]
-pub struct ChatCompletion {
-	// id: String,
-	// object: String,
-	// created: usize,
-	choices: Vec<Choice>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct Choice {
-	delta: Delta,
-	// index: usize,
-	// finish_reason: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct Delta {
-	content: Option<String>,
-}
-
 #[derive(Template)]
 #[template(path = "prompt.txt")]
 struct AiPrompt<'a> {
@@ -53,6 +56,7 @@ struct AiPrompt<'a> {
 	error_msg: &'a str,
 	additional_prompt: &'a str,
 	set_locale: &'a str,
+	context: &'a str,
 }
 
 #[derive(Template)]
@@ -70,6 +74,19 @@ pub async fn ai_suggestion(last_command: &str, error_msg: &str, locale: &str) {
 		}
 	};
 
+	let cache = Cache::new(&conf.cache, last_command, error_msg, &conf.model, &conf.url);
+	if let Some(response) = cache.get() {
+		#[cfg(debug_assertions)]
+		eprintln!("AI module: Using cached response");
+		let mut buffer = buffer::Buffer::new();
+		// same as streamed, for the explanation to be printed
+		for line in response.split_inclusive('\n') {
+			buffer.proc(line);
+		}
+		print_suggestions(&buffer);
+		return;
+	}
+
 	let char_limit = 500;
 
 	let error_msg = if error_msg.chars().count() > 500 + 3 {
@@ -91,7 +108,7 @@ pub async fn ai_suggestion(last_command: &str, error_msg: &str, locale: &str) {
 	};
 
 	let user_locale = {
-		let locale = std::env::var("_PR_AI_LOCALE").unwrap_or_else(|_| locale.to_string());
+		let locale = conf.locale.clone().unwrap_or(locale.to_string());
 		if locale.len() < 2 {
 			"en-US".to_string()
 		} else {
@@ -105,17 +122,17 @@ pub async fn ai_suggestion(last_command: &str, error_msg: &str, locale: &str) {
 		"".to_string()
 	};
 
-	let addtional_prompt = if std::env::var("_PR_AI_ADDITIONAL_PROMPT").is_ok() {
-		std::env::var("_PR_AI_ADDITIONAL_PROMPT").unwrap()
-	} else {
-		"".to_string()
-	};
+	let addtional_prompt = conf.additional_prompt.clone().unwrap_or_default();
+
+	let shell = std::env::var("_PR_SHELL").unwrap_or_default();
+	let context = context::collect(&conf.context, &shell, conf.redactor.as_ref());
 
 	let ai_prompt = AiPrompt {
 		last_command,
 		error_msg: &error_msg,
 		additional_prompt: &addtional_prompt,
 		set_locale: &set_locale,
+		context: &context,
 	}
 	.render()
 	.unwrap()
@@ -125,59 +142,38 @@ pub async fn ai_suggestion(last_command: &str, error_msg: &str, locale: &str) {
 	#[cfg(debug_assertions)]
 	eprintln!("AI module: AI prompt: {}", ai_prompt);
 
-	let extra: Option<String> = std::env::var("_PR_AI_EXTRA").ok();
-	let extra_body: Option<String> = std::env::var("_PR_AI_EXTRA_BODY").ok();
-
-	let body = Request { extra, extra_body };
+	let body = Request {
+		extra: conf.extra.clone(),
+		extra_body: conf.extra_body.clone(),
+	};
 	let body = body.render().unwrap();
-	let mut json_body: Value = serde_json::from_str(&body).unwrap();
-	json_body["model"] = Value::String(conf.model);
-	json_body["messages"][0]["content"] = Value::String(ai_prompt);
-	json_body["stream"] = Value::Bool(true);
-
-	let client = reqwest::Client::new();
-	let res = client
-		.post(&conf.url)
-		.body(serde_json::to_string(&json_body).unwrap())
-		.header("Content-Type", "application/json")
-		.bearer_auth(&conf.key)
-		.send()
-		.await
-		.unwrap();
-
-	if res.status() != 200 {
-		eprintln!("AI module: Status code: {}", res.status());
-		eprintln!(
-			"AI module: Error message:\n  {}",
-			res.text().await.unwrap().replace("\n", "\n  ")
-		);
-		return;
-	}
-
-	let mut stream = res.bytes_stream();
-	let mut json_buffer = String::new();
-	let mut buffer = buffer::Buffer::new();
-
-	while let Some(item) = stream.next().await {
-		let item = item.unwrap();
-		let str = std::str::from_utf8(&item).unwrap();
-
-		json_buffer.push_str(str);
-
-		if !json_buffer.contains("\n\ndata: ") {
-			continue;
+	let mut json_body: Value = match serde_json::from_str(&body) {
+		Ok(json_body) => json_body,
+		Err(e) => {
+			eprintln!("AI module: Invalid extra fields in the request: {}", e);
+			return;
 		}
+	};
+	conf.backend
+		.fill_body(&mut json_body, conf.model.clone(), ai_prompt);
 
-		while json_buffer.contains("\n\ndata: ") {
-			proc_stream_buffer(&mut json_buffer, &mut buffer);
+	let mut buffer = buffer::Buffer::new();
+	match request_suggestions(&conf, &json_body, &mut buffer).await {
+		Ok(response) => cache.set(&response),
+		Err(e) => {
+			// the streamed explanation may have been left without a new line
+			if buffer.pending_line() {
+				eprintln!();
+			}
+			eprintln!("AI module: {}", e);
+			return;
 		}
 	}
-	// remaining buffer
-	json_buffer.push_str("\n\ndata: "); // hackey
-	while json_buffer.contains("\n\ndata: ") {
-		proc_stream_buffer(&mut json_buffer, &mut buffer);
-	}
 
+	print_suggestions(&buffer);
+}
+
+fn print_suggestions(buffer: &buffer::Buffer) {
 	let suggestions = buffer
 		.buf
 		.trim()
@@ -190,74 +186,234 @@ pub async fn ai_suggestion(last_command: &str, error_msg: &str, locale: &str) {
 	println!("{}", suggestions);
 }
 
-fn proc_stream_buffer(json_buffer: &mut String, buffer: &mut buffer::Buffer) {
-	let data_loc = json_buffer.find("\n\ndata: ").unwrap();
-	let binding = json_buffer.clone();
-	let split = binding.split_at(data_loc);
-	let json = split.0;
-	*json_buffer = split.1.trim_start().to_string();
-
-	proc_json(json, buffer);
-}
+async fn request_suggestions(
+	conf: &Conf,
+	json_body: &Value,
+	buffer: &mut buffer::Buffer,
+) -> Result<String, String> {
+	let mut client = reqwest::Client::builder()
+		.connect_timeout(CONNECT_TIMEOUT)
+		.read_timeout(READ_TIMEOUT);
+	if let Some(timeout) = conf.timeout {
+		client = client.timeout(Duration::from_secs(timeout));
+	}
+	let client = client.build().map_err(error_message)?;
+	let body = json_body.to_string();
+
+	let mut attempt = 0;
+	let res = loop {
+		let mut request = client
+			.post(&conf.url)
+			.body(body.clone())
+			.header("Content-Type", "application/json");
+		if !conf.key.is_empty() {
+			request = request.bearer_auth(&conf.key);
+		}
 
-fn proc_json(res: &str, buffer: &mut buffer::Buffer) {
-	if let Some(data) = res.trim().strip_prefix("data: ") {
-		if data == "[DONE]" {
-			return;
+		let (retry, error) = match request.send().await {
+			Ok(res) if res.status().is_success() => break res,
+			Ok(res) => {
+				let status = res.status();
+				let retry_after = res
+					.headers()
+					.get(reqwest::header::RETRY_AFTER)
+					.and_then(|value| value.to_str().ok())
+					.and_then(|value| value.parse::<u64>().ok())
+					.map(Duration::from_secs);
+				let retry = (status.as_u16() == 429 || status.is_server_error())
+					.then(|| retry_after.unwrap_or(backoff(attempt)));
+				let body = res.text().await.unwrap_or_default();
+				let error = match response_error(&body) {
+					message if message.is_empty() => status.to_string(),
+					message => format!("{}: {}", status, message),
+				};
+				(retry, error)
+			}
+			Err(e) => {
+				let retry = (e.is_connect() || e.is_timeout()).then(|| backoff(attempt));
+				(retry, error_message(e))
+			}
+		};
+		match retry {
+			Some(delay) if attempt < conf.retries => {
+				#[cfg(debug_assertions)]
+				eprintln!("AI module: {}, retrying in {:?}", error, delay);
+				tokio::time::sleep(delay.min(MAX_BACKOFF)).await;
+				attempt += 1;
+			}
+			_ => return Err(error),
 		}
-		let json = serde_json::from_str::<ChatCompletion>(data)
-			.unwrap_or_else(|_| panic!("AI module: Failed to parse JSON content: {}", data));
-		let choice = json.choices.first().expect("AI module: No choices found");
-		if let Some(content) = &choice.delta.content {
-			buffer.proc(content);
+	};
+
+	let mut stream = res.bytes_stream();
+	let mut line_buffer: Vec<u8> = vec![];
+	let mut response = String::new();
+
+	while let Some(item) = stream.next().await {
+		let item = item.map_err(error_message)?;
+		line_buffer.extend_from_slice(&item);
+
+		// both event streams and JSON lines are processed line by line, a
+		// chunk can end in the middle of a character
+		while let Some(end) = line_buffer.iter().position(|&b| b == b'\n') {
+			let line = line_buffer.drain(..=end).collect::<Vec<u8>>();
+			if let Some(content) = conf.backend.content(&String::from_utf8_lossy(&line))? {
+				buffer.proc(&content);
+				response.push_str(&content);
+			}
 		}
 	}
+	// remaining buffer
+	if let Some(content) = conf
+		.backend
+		.content(&String::from_utf8_lossy(&line_buffer))?
+	{
+		buffer.proc(&content);
+		response.push_str(&content);
+	}
+	Ok(response)
+}
+
+fn backoff(attempt: u32) -> Duration {
+	// `retries` is user configured, keep large ones from overflowing
+	Duration::from_millis(2u64.saturating_pow(attempt).saturating_mul(500))
+}
+
+/// The error and its causes on a single line
+fn error_message(error: reqwest::Error) -> String {
+	let error = error.without_url();
+	let mut message = error.to_string();
+	let mut source = std::error::Error::source(&error);
+	while let Some(error) = source {
+		message = format!("{}: {}", message, error);
+		source = error.source();
+	}
+	message
+}
+
+/// The message of an error response, usually `{"error": {"message": ...}}`
+fn response_error(body: &str) -> String {
+	let json = serde_json::from_str::<Value>(body).unwrap_or_default();
+	let error = &json["error"];
+	let message = error["message"]
+		.as_str()
+		.or(error.as_str())
+		.or(json["message"].as_str())
+		.unwrap_or(body);
+	let message = message.split_whitespace().collect::<Vec<&str>>().join(" ");
+	if message.chars().count() > 200 {
+		format!("{}...", message.chars().take(200).collect::<String>())
+	} else {
+		message
+	}
+}
+
+/// Environment variable, then the config file, then the compile time default
+fn setting(var: &str, profile: Option<String>, default: Option<&str>) -> Option<String> {
+	std::env::var(var)
+		.ok()
+		.or(profile)
+		.or_else(|| default.map(|default| default.to_string()))
 }
 
 impl Conf {
 	pub fn new() -> Option<Self> {
+		let settings = match load_config() {
+			Ok(settings) => settings,
+			Err(e) => {
+				eprintln!("AI module: {}", e);
+				return None;
+			}
+		};
+		let profile = settings.profile;
+
+		let backend = setting(
+			"_PR_AI_BACKEND",
+			profile.backend.clone(),
+			option_env!("_DEF_PR_AI_BACKEND"),
+		)
+		.unwrap_or("openai".to_string());
+		let backend = match Backend::from_name(&backend) {
+			Some(backend) => backend,
+			None => {
+				eprintln!("AI module: Unknown backend: {}", backend);
+				return None;
+			}
+		};
+
+		// the key file or command is only read when needed
 		let key = match std::env::var("_PR_AI_API_KEY") {
-			Ok(key) => key,
-			Err(_) => {
-				if let Some(key) = option_env!("_DEF_PR_AI_API_KEY") {
-					key.to_string()
-				} else {
-					"Y29uZ3JhdHVsYXRpb25zLCB5b3UgZm91bmQgdGhlIHNlY3JldCE=".to_string()
+			Ok(key) => Some(key),
+			Err(_) => match profile.key() {
+				Ok(key) => key,
+				Err(e) => {
+					eprintln!("AI module: {}", e);
+					return None;
 				}
-			}
+			},
+		};
+		let key = match key.or(option_env!("_DEF_PR_AI_API_KEY").map(|key| key.to_string())) {
+			Some(key) => key,
+			None if backend.is_local() => "".to_string(),
+			None => "Y29uZ3JhdHVsYXRpb25zLCB5b3UgZm91bmQgdGhlIHNlY3JldCE=".to_string(),
 		};
-		if key.is_empty() {
+		if key.is_empty() && !backend.is_local() {
 			return None;
 		}
 
-		let url = match std::env::var("_PR_AI_URL") {
-			Ok(url) => url,
-			Err(_) => {
-				if let Some(url) = option_env!("_DEF_PR_AI_URL") {
-					url.to_string()
-				} else {
-					"https://pay-respects-serverless.iffse.eu.org/".to_string()
-				}
-			}
-		};
+		let url = setting("_PR_AI_URL", profile.url, option_env!("_DEF_PR_AI_URL"))
+			.or(backend.default_url().map(|url| url.to_string()))
+			.unwrap_or("https://pay-respects-serverless.iffse.eu.org/".to_string());
 		if url.is_empty() {
 			return None;
 		}
 
-		let model = match std::env::var("_PR_AI_MODEL") {
-			Ok(model) => model,
-			Err(_) => {
-				if let Some(model) = option_env!("_DEF_PR_AI_MODEL") {
-					model.to_string()
-				} else {
-					"uwu".to_string()
-				}
-			}
-		};
+		let model = setting(
+			"_PR_AI_MODEL",
+			profile.model,
+			option_env!("_DEF_PR_AI_MODEL"),
+		)
+		.unwrap_or("uwu".to_string());
 		if model.is_empty() {
 			return None;
 		}
 
-		Some(Conf { key, url, model })
+		let timeout = match std::env::var("_PR_AI_TIMEOUT") {
+			Ok(timeout) => match timeout.parse::<u64>() {
+				Ok(timeout) => Some(timeout),
+				Err(_) => {
+					eprintln!("AI module: Invalid timeout: {}", timeout);
+					return None;
+				}
+			},
+			Err(_) => profile.timeout,
+		};
+
+		let retries = match std::env::var("_PR_AI_RETRIES") {
+			Ok(retries) => match retries.parse::<u32>() {
+				Ok(retries) => retries,
+				Err(_) => {
+					eprintln!("AI module: Invalid retries: {}", retries);
+					return None;
+				}
+			},
+			Err(_) => profile.retries.unwrap_or(2),
+		};
+
+		Some(Conf {
+			key,
+			url,
+			model,
+			backend,
+			timeout,
+			retries,
+			extra: setting("_PR_AI_EXTRA", profile.extra, None),
+			extra_body: setting("_PR_AI_EXTRA_BODY", profile.extra_body, None),
+			additional_prompt: setting("_PR_AI_ADDITIONAL_PROMPT", profile.additional_prompt, None),
+			locale: setting("_PR_AI_LOCALE", profile.locale, None),
+			context: settings.context,
+			redactor: settings.redactor,
+			cache: settings.cache,
+		})
 	}
 }
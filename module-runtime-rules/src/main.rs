@@ -15,17 +15,23 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 mod config;
-mod replaces;
-mod rules;
+mod rule_tests;
+use pay_respects_ruleset::{Context, RuleSet};
 use pay_respects_utils::{
 	evals::{split_command, split_comment},
-	files::get_path_files,
-	modes::run_mode,
+	files::{get_path_files, rule_dirs},
+	modes::{Mode, run_mode},
 };
 
 use crate::config::{get_target_rule, load_config};
 
 fn main() -> Result<(), std::io::Error> {
+	let mut args = std::env::args().skip(1);
+	if args.next().as_deref() == Some("--test-rules") {
+		let passed = rule_tests::run(args.next());
+		std::process::exit(if passed { 0 } else { 1 });
+	}
+
 	let executable = std::env::var("_PR_COMMAND").expect("_PR_COMMAND not set");
 	let shell = std::env::var("_PR_SHELL").expect("_PR_SHELL not set");
 	let mut last_command = std::env::var("_PR_LAST_COMMAND").expect("_PR_LAST_COMMAND not set");
@@ -54,37 +60,17 @@ fn main() -> Result<(), std::io::Error> {
 	let config = load_config();
 	let target_rule = get_target_rule(&executable, &config);
 
-	let mut runned_rules = vec![];
-	let mut pending_rules = vec![target_rule];
-
-	while let Some(executable) = pending_rules.pop() {
-		if runned_rules.contains(&executable) {
-			continue;
-		}
-		runned_rules.push(executable.clone());
-		let extends = rules::runtime_match(
-			&mode,
-			&executable,
-			&shell,
-			&last_command,
-			&error_msg,
-			&executables,
-		);
-		if let Some(extends) = extends {
-			for extend in extends {
-				if !runned_rules.contains(&extend) {
-					pending_rules.push(extend);
-				}
-			}
-		}
+	let context = Context {
+		shell: &shell,
+		command: &last_command,
+		error: &error_msg,
+		executables: &executables,
+		inline: matches!(mode, Mode::Inline),
+	};
+	let rules = RuleSet::load(&rule_dirs());
+	for (_, suggestion) in rules.suggestions(&target_rule, &context) {
+		print!("{}", suggestion.command);
+		print!("<_PR_BR>");
 	}
-	rules::runtime_match(
-		&mode,
-		"_PR_GENERAL",
-		&shell,
-		&last_command,
-		&error_msg,
-		&executables,
-	);
 	Ok(())
 }
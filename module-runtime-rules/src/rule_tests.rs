@@ -0,0 +1,73 @@
+use pay_respects_ruleset::{Context, Rule};
+use pay_respects_utils::files::rule_dirs;
+
+/// Runs the `[[test]]` entries of the rule file or directory at `path`, or of
+/// every rule directory if not given. Returns whether all tests passed.
+pub fn run(path: Option<String>) -> bool {
+	let files = match path {
+		Some(path) if std::path::Path::new(&path).is_file() => vec![path],
+		Some(path) => rule_files(&path),
+		None => rule_dirs().iter().flat_map(|dir| rule_files(dir)).collect(),
+	};
+
+	let mut passed = 0;
+	let mut failed = 0;
+	for file in files {
+		let rule = match Rule::from_file(&file) {
+			Ok(rule) => rule,
+			Err(e) => {
+				eprintln!("runtime-rules: Failed to read {}: {}", file, e);
+				failed += 1;
+				continue;
+			}
+		};
+		for (i, test) in rule.test.iter().enumerate() {
+			let shell = test.shell.as_deref().unwrap_or("bash");
+			let error = test
+				.error
+				.split_whitespace()
+				.collect::<Vec<&str>>()
+				.join(" ");
+			let context = Context {
+				shell,
+				command: &test.command,
+				error: &error,
+				executables: &test.executables,
+				inline: false,
+			};
+			let suggestions = rule
+				.suggestions(&context)
+				.iter()
+				.map(|suggestion| suggestion.command.trim().to_string())
+				.collect::<Vec<String>>();
+
+			if suggestions.contains(&test.expected) {
+				passed += 1;
+				println!("{} [{}] ... ok", file, i);
+			} else {
+				failed += 1;
+				println!("{} [{}] ... FAILED", file, i);
+				println!("  command:  {}", test.command);
+				println!("  expected: {}", test.expected);
+				println!("  got:      {:?}", suggestions);
+			}
+		}
+	}
+	println!("\n{} passed, {} failed", passed, failed);
+	failed == 0
+}
+
+fn rule_files(dir: &str) -> Vec<String> {
+	let entries = match std::fs::read_dir(dir) {
+		Ok(entries) => entries,
+		Err(_) => return vec![],
+	};
+	let mut files = entries
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+		.map(|path| path.to_string_lossy().to_string())
+		.collect::<Vec<String>>();
+	files.sort_unstable();
+	files
+}
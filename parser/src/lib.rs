@@ -3,15 +3,13 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 use itertools::sorted_unstable;
-use pay_respects_utils::strings::split_unescaped_character;
+use pay_respects_ruleset::{Condition, Rule, split_conditions};
 use std::path::Path;
 
 use proc_macro::TokenStream;
 use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 
-mod replaces;
-
 #[proc_macro]
 pub fn parse_rules(input: TokenStream) -> TokenStream {
 	let rules = get_rules(input.to_string().trim_matches('"'));
@@ -24,17 +22,10 @@ pub fn parse_inline_rules(input: TokenStream) -> TokenStream {
 	gen_inline_rules(&rules)
 }
 
-#[derive(serde::Deserialize)]
-struct Rule {
-	command: String,
-	extends: Option<Vec<String>>,
-	match_err: Vec<MatchError>,
-}
-
-#[derive(serde::Deserialize)]
-struct MatchError {
-	pattern: Option<Vec<String>>,
-	suggest: Vec<String>,
+#[proc_macro]
+pub fn parse_rule_tests(input: TokenStream) -> TokenStream {
+	let rules = get_rules(input.to_string().trim_matches('"'));
+	gen_rule_tests(&rules)
 }
 
 fn get_rules(directory: &str) -> Vec<Rule> {
@@ -52,8 +43,9 @@ fn get_rules(directory: &str) -> Vec<Rule> {
 
 	let mut rules = Vec::new();
 	for path in files {
-		let rule_file = parse_file(Path::new(&path));
-		rules.push(rule_file);
+		let rule = Rule::from_file(&path)
+			.unwrap_or_else(|e| panic!("Failed to parse rule {}: {}", path, e));
+		rules.push(rule);
 	}
 	rules
 }
@@ -64,14 +56,16 @@ fn gen_match_rules(rules: &[Rule]) -> TokenStream {
 
 	let mut matches_tokens = Vec::new();
 
-	for match_err in command_matches {
+	for (rule, match_err) in rules.iter().zip(command_matches) {
+		let rule_name = &rule.command;
+		let rule_path = &rule.path;
 		let mut suggestion_tokens = Vec::new();
 		let mut patterns_tokens = Vec::new();
 		for (pattern, suggests) in match_err {
 			// let mut match_condition = Vec::new();
 			let mut pattern_suggestions = Vec::new();
 			for suggest in suggests {
-				let (suggestion_no_condition, mut conditions) = parse_conditions(&suggest);
+				let (suggestion_no_condition, mut conditions) = split_conditions(&suggest);
 				if let Some(conditions) = &mut conditions {
 					conditions.retain(|x| x != "INLINE");
 				}
@@ -85,28 +79,43 @@ fn gen_match_rules(rules: &[Rule]) -> TokenStream {
 
 			suggestion_tokens.push(match_tokens);
 
+			// (normalized pattern, pattern as written in the rule)
 			if let Some(pattern) = pattern {
-				let string_patterns = pattern.join("\"###, r###\"");
+				let string_patterns = pattern
+					.iter()
+					.map(|(pattern, source)| {
+						format!("(r###\"{}\"###, r###\"{}\"###)", pattern, source)
+					})
+					.collect::<Vec<String>>()
+					.join(", ");
 				let string_patterns: TokenStream2 =
-					format!("[r###\"{}\"###]", string_patterns).parse().unwrap();
+					format!("[{}]", string_patterns).parse().unwrap();
 				patterns_tokens.push(string_patterns);
 			} else {
-				patterns_tokens.push("[\"\"]".parse().unwrap());
+				patterns_tokens.push("[(\"\", \"\")]".parse().unwrap());
 			}
 		}
 
+		// `matched` keeps the rule and pattern that produced each candidate
 		matches_tokens.push(quote! {
 			#(
-			for pattern in #patterns_tokens {
+			for (pattern, source) in #patterns_tokens {
 				if error_lower.contains(pattern) {
 					#suggestion_tokens;
+					matched.resize(candidates.len(), (#rule_name, #rule_path, source));
 					break;
 				};
 			})*
 		})
 	}
 	quote! {
-		let mut last_command = last_command.to_string();
+		let context = Context {
+			shell,
+			command: last_command,
+			error: error_msg,
+			executables,
+			inline: false,
+		};
 		match executable {
 			#(
 			#command => {
@@ -131,7 +140,7 @@ fn gen_inline_rules(rules: &[Rule]) -> TokenStream {
 			// let mut match_condition = Vec::new();
 			let mut pattern_suggestions = Vec::new();
 			for suggest in suggests {
-				let (suggestion_no_condition, mut conditions) = parse_conditions(&suggest);
+				let (suggestion_no_condition, mut conditions) = split_conditions(&suggest);
 				if conditions.is_none() {
 					continue;
 				}
@@ -164,7 +173,13 @@ fn gen_inline_rules(rules: &[Rule]) -> TokenStream {
 		})
 	}
 	quote! {
-		let mut last_command = last_command.to_string();
+		let context = Context {
+			shell,
+			command: last_command,
+			error: error_msg,
+			executables,
+			inline: true,
+		};
 		match executable {
 			#(
 			#command => {
@@ -177,8 +192,44 @@ fn gen_inline_rules(rules: &[Rule]) -> TokenStream {
 	.into()
 }
 
+/// A `#[test]` for each `[[test]]` entry, calling `check_rule` from the
+/// scope of the macro with the rule file, to compare with the interpreter
+fn gen_rule_tests(rules: &[Rule]) -> TokenStream {
+	let mut tests_tokens = Vec::new();
+
+	for rule in rules {
+		let file_name = Path::new(&rule.path)
+			.file_stem()
+			.unwrap()
+			.to_string_lossy()
+			.to_lowercase()
+			.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+		let rule_name = &rule.command;
+		let rule_path = &rule.path;
+
+		for (i, test) in rule.test.iter().enumerate() {
+			let test_name: TokenStream2 = format!("rule_{}_{}", file_name, i).parse().unwrap();
+			let command = &test.command;
+			let error = &test.error;
+			let executables = &test.executables;
+			let shell = test.shell.as_deref().unwrap_or("bash");
+			let expected = &test.expected;
+			tests_tokens.push(quote! {
+				#[test]
+				fn #test_name() {
+					check_rule(#rule_name, #rule_path, #command, #error, &[#(#executables),*], #shell, #expected);
+				}
+			});
+		}
+	}
+	quote! {
+		#(#tests_tokens)*
+	}
+	.into()
+}
+
 #[allow(clippy::type_complexity)]
-fn parse_match_err(rules: &[Rule]) -> Vec<Vec<(Option<Vec<String>>, Vec<String>)>> {
+fn parse_match_err(rules: &[Rule]) -> Vec<Vec<(Option<Vec<(String, String)>>, Vec<String>)>> {
 	rules
 		.iter()
 		.map(|x| {
@@ -189,12 +240,14 @@ fn parse_match_err(rules: &[Rule]) -> Vec<Vec<(Option<Vec<String>>, Vec<String>)
 						let pattern = pattern
 							.iter()
 							.map(|x| {
-								x.split_whitespace()
+								let normalized = x
+									.split_whitespace()
 									.collect::<Vec<_>>()
 									.join("")
-									.to_lowercase()
+									.to_lowercase();
+								(normalized, x.to_string())
 							})
-							.collect::<Vec<String>>();
+							.collect::<Vec<(String, String)>>();
 						Some(pattern)
 					} else {
 						None
@@ -206,9 +259,9 @@ fn parse_match_err(rules: &[Rule]) -> Vec<Vec<(Option<Vec<String>>, Vec<String>)
 						.collect::<Vec<String>>();
 					(pattern, suggests)
 				})
-				.collect::<Vec<(Option<Vec<String>>, Vec<String>)>>()
+				.collect::<Vec<(Option<Vec<(String, String)>>, Vec<String>)>>()
 		})
-		.collect::<Vec<Vec<(Option<Vec<String>>, Vec<String>)>>>()
+		.collect::<Vec<Vec<(Option<Vec<(String, String)>>, Vec<String>)>>>()
 }
 
 fn rule_commands(rules: &[Rule]) -> Vec<TokenStream2> {
@@ -234,66 +287,28 @@ fn rule_commands(rules: &[Rule]) -> Vec<TokenStream2> {
 		.collect::<Vec<TokenStream2>>()
 }
 
-fn parse_file(file: &Path) -> Rule {
-	let file = std::fs::read_to_string(file).expect("Failed to read file.");
-	toml::from_str(&file).expect("Failed to parse toml.")
-}
-
-fn parse_conditions(suggest: &str) -> (String, Option<Vec<String>>) {
-	if suggest.starts_with('#') {
-		let mut lines = suggest.lines().collect::<Vec<&str>>();
-		let mut conditions = String::new();
-		for (i, line) in lines[0..].iter().enumerate() {
-			conditions.push_str(line);
-			if line.ends_with(']') {
-				lines = lines[i + 1..].to_vec();
-				break;
-			}
-		}
-
-		let conditions = conditions
-			.trim_start_matches(['#', '['])
-			.trim_end_matches(']');
-		let conditions = split_unescaped_character(conditions, ',')
-			.into_iter()
-			.map(|x| x.trim().to_string())
-			.collect::<Vec<String>>();
-		let suggest = lines.join("\n");
-		return (suggest, Some(conditions));
-	}
-	(suggest.to_owned(), None)
-}
-
 fn tokenize_conditions(conditions: &[String]) -> Vec<TokenStream2> {
 	let mut eval_conditions = Vec::new();
 	for condition in conditions {
-		let (mut condition, arg) = condition.split_once('(').unwrap();
-		condition = condition.trim();
-		// remove only the last character which is ')'
-		// other ')' are kept for regex
-		let arg = arg
-			.to_string()
-			.chars()
-			.take(arg.len() - 1)
-			.collect::<String>();
-
-		let reverse = match condition.starts_with('!') {
-			true => {
-				condition = condition.trim_start_matches('!');
-				true
-			}
-			false => false,
-		};
-		let evaluated_condition = eval_condition(condition, &arg);
-
-		eval_conditions.push(quote! {#evaluated_condition == !#reverse});
+		let condition = Condition::parse(condition).unwrap_or_else(|e| panic!("{}", e));
+		let (name, arg, negated) = (&condition.name, &condition.arg, condition.negated);
+
+		// parenthesized, as struct literals are not allowed in `if` conditions
+		eval_conditions.push(quote! {
+			(Condition {
+				name: #name.to_string(),
+				arg: #arg.to_string(),
+				negated: #negated,
+			})
+			.eval(&context, &error_lower, &split)
+		});
 	}
 	eval_conditions
 }
 
 fn parse_suggestion(suggestion: &str, conditions: Option<Vec<String>>) -> TokenStream2 {
 	if conditions.is_none() {
-		return eval_suggest(suggestion);
+		return eval_suggest(suggestion, &[]);
 	}
 	let (conditions, is_function) = {
 		let mut conditions = conditions.unwrap();
@@ -309,9 +324,10 @@ fn parse_suggestion(suggestion: &str, conditions: Option<Vec<String>>) -> TokenS
 		let suggestion: TokenStream2 = suggestion.trim_matches('"').parse().unwrap();
 		quote! {
 			rules_function(#suggestion, &error_msg, &error_lower, &shell, &last_command, &executables, &split, &mut candidates, data);
+			details.resize(candidates.len(), (&[#(#conditions),*], vec![]));
 		}
 	} else {
-		eval_suggest(suggestion)
+		eval_suggest(suggestion, &conditions)
 	};
 
 	if conditions.is_empty() {
@@ -326,56 +342,11 @@ fn parse_suggestion(suggestion: &str, conditions: Option<Vec<String>>) -> TokenS
 	}
 }
 
-fn eval_condition(condition: &str, arg: &str) -> TokenStream2 {
-	match condition {
-		"executable" => quote! {executables.contains(&#arg.to_string())},
-		"err_contains" => quote! {regex_match(#arg, &error_lower)},
-		"cmd_contains" => quote! {regex_match(#arg, &last_command)},
-		"min_length" => quote! {(split.len() >= #arg.parse::<usize>().unwrap())},
-		"length" => quote! {(split.len() == #arg.parse::<usize>().unwrap())},
-		"max_length" => quote! {(split.len() <= #arg.parse::<usize>().unwrap() + 1)},
-		"shell" => quote! {(shell == #arg)},
-		_ => unreachable!("Unknown condition when evaluation condition: {}", condition),
-	}
-}
-
-fn eval_suggest(suggest: &str) -> TokenStream2 {
-	let mut suggest = suggest.to_owned();
-	if suggest.contains("{{command}}") {
-		suggest = suggest.replace("{{command}}", "{last_command}");
-	}
-
-	let mut replace_list = Vec::new();
-	let mut select_list = Vec::new();
-	let mut opt_list = Vec::new();
-	let mut cmd_list = Vec::new();
-
-	replaces::opts(&mut suggest, &mut replace_list, &mut opt_list);
-	replaces::cmd_reg(&mut suggest, &mut replace_list);
-	replaces::err(&mut suggest, &mut replace_list);
-	replaces::command(&mut suggest, &mut replace_list);
-	replaces::shell(&mut suggest, &mut cmd_list);
-	replaces::typo(&mut suggest, &mut replace_list);
-	replaces::select(&mut suggest, &mut select_list);
-	replaces::shell_tag(&mut suggest, &mut replace_list, &cmd_list);
-
-	let suggests = if select_list.is_empty() {
-		quote! {
-			candidates.push(format!{#suggest, #(#replace_list),*});
-		}
-	} else {
-		quote! {
-			#(#select_list)*
-			let suggest = format!{#suggest, #(#replace_list),*};
-			for select in selects {
-				let suggest = suggest.replace("{{selection}}", &select);
-				candidates.push(suggest);
-			}
-		}
-	};
-
+/// `details` keeps the conditions and placeholder values of each candidate
+fn eval_suggest(suggest: &str, conditions: &[String]) -> TokenStream2 {
 	quote! {
-		#(#opt_list)*
-		#suggests
+		let (suggests, expansions) = eval_suggest_explained(#suggest, &context);
+		candidates.extend(suggests);
+		details.resize(candidates.len(), (&[#(#conditions),*], expansions));
 	}
 }
@@ -0,0 +1,214 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use regex_lite::Regex;
+
+use crate::conditions::{Condition, IDENTIFIERS, split_conditions};
+use crate::replaces::{bracket, list};
+
+/// Returns the problems of a suggestion, along with the text they were found in
+pub fn check_suggest(suggest: &str) -> Vec<(String, String)> {
+	let mut problems = vec![];
+
+	let body = if suggest.starts_with('#') {
+		if !suggest.lines().any(|line| line.ends_with(']')) {
+			let first = suggest.lines().next().unwrap_or("").to_string();
+			problems.push((first, "condition list is not closed with `]`".to_string()));
+			return problems;
+		}
+		let (body, conditions) = split_conditions(suggest);
+		for condition in conditions.unwrap_or_default() {
+			if let Some(message) = check_condition(&condition) {
+				problems.push((condition, message));
+			}
+		}
+		body
+	} else {
+		suggest.to_string()
+	};
+
+	let mut starts = vec![];
+	let mut i = 0;
+	while i + 1 < body.len() {
+		match &body.as_bytes()[i..i + 2] {
+			b"{{" => {
+				starts.push(i);
+				i += 2;
+			}
+			b"}}" => {
+				match starts.pop() {
+					Some(start) => {
+						let placeholder = &body[start..i + 2];
+						if let Some(message) =
+							check_placeholder(&placeholder[2..placeholder.len() - 2])
+						{
+							problems.push((placeholder.to_string(), message));
+						}
+					}
+					None => {
+						problems.push(("}}".to_string(), "unbalanced `}}`".to_string()));
+					}
+				}
+				i += 2;
+			}
+			_ => i += 1,
+		}
+	}
+	for start in starts {
+		let placeholder = body[start..].lines().next().unwrap_or("").to_string();
+		problems.push((
+			placeholder,
+			"placeholder is not closed with `}}`".to_string(),
+		));
+	}
+	problems
+}
+
+fn check_condition(condition: &str) -> Option<String> {
+	if IDENTIFIERS.contains(&condition) {
+		return None;
+	}
+	let condition = match Condition::parse(condition) {
+		Ok(condition) => condition,
+		Err(message) => return Some(message),
+	};
+	let (name, arg) = (condition.name.as_str(), condition.arg.as_str());
+	match name {
+		"min_length" | "length" | "max_length" if arg.trim().parse::<usize>().is_err() => {
+			Some(format!("`{}` expects a number, got `{}`", name, arg))
+		}
+		// same as `regex_match`
+		"err_contains" | "cmd_contains" => {
+			check_regex(&arg.split_whitespace().collect::<Vec<&str>>().join(""))
+		}
+		_ => None,
+	}
+}
+
+fn check_placeholder(placeholder: &str) -> Option<String> {
+	if placeholder == "command" || placeholder == "selection" {
+		return None;
+	}
+	for prefix in ["opt::", "cmd::", "err::"] {
+		if let Some(regex) = placeholder.strip_prefix(prefix) {
+			return check_regex(regex.trim());
+		}
+	}
+	if let Some(index) = placeholder.strip_prefix("command") {
+		let index = index.trim();
+		return match index.strip_prefix('[').and_then(|x| x.strip_suffix(']')) {
+			Some(index) if valid_index(index, true) => None,
+			_ => Some(format!("bad command index `{}`", index)),
+		};
+	}
+	if let Some(args) = placeholder.strip_prefix("typo") {
+		return check_list_placeholder("typo", args, true, true);
+	}
+	if let Some(args) = placeholder.strip_prefix("select") {
+		return check_list_placeholder("select", args, false, false);
+	}
+	if let Some(args) = placeholder.strip_prefix("shell") {
+		let args = args.trim();
+		if !(args.starts_with('(') && args.ends_with(')')) {
+			return Some("`shell` expects a command in parentheses".to_string());
+		}
+		return None;
+	}
+	let name = placeholder
+		.split(['[', '(', ':'])
+		.next()
+		.unwrap_or(placeholder);
+	Some(format!("unknown placeholder `{{{{{}}}}}`", name.trim()))
+}
+
+/// Checks `[index](candidates)` of `typo` and `select`, as read by `bracket`
+/// and `list`
+fn check_list_placeholder(
+	name: &str,
+	args: &str,
+	index_required: bool,
+	range: bool,
+) -> Option<String> {
+	let args = args.trim();
+	if args.starts_with('[') {
+		let Some(index) = bracket(args) else {
+			return Some(format!("`{}` index is not closed with `]`", name));
+		};
+		if !valid_index(index, range) {
+			return Some(format!("bad {} index `[{}]`", name, index));
+		}
+	} else if index_required {
+		return Some(format!("`{}` requires an index, e.g. `{}[1]`", name, name));
+	}
+	if !args.ends_with(')') || list(args).is_empty() {
+		return Some(format!("`{}` requires candidates in parentheses", name));
+	}
+	None
+}
+
+fn valid_index(index: &str, range: bool) -> bool {
+	let is_number = |x: &str| x.trim().parse::<i32>().is_ok();
+	match index.split_once(':') {
+		Some((start, end)) if range => {
+			(start.trim().is_empty() || is_number(start))
+				&& (end.trim().is_empty() || is_number(end))
+		}
+		Some(_) => false,
+		None => is_number(index),
+	}
+}
+
+fn check_regex(regex: &str) -> Option<String> {
+	match Regex::new(regex) {
+		Ok(_) => None,
+		Err(err) => Some(format!(
+			"invalid regex `{}`: {}",
+			regex,
+			err.to_string().lines().last().unwrap_or("")
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::check_suggest;
+
+	#[test]
+	fn test_check_suggest() {
+		assert!(check_suggest("{{command[0]}} {{typo[1](add, am)}} {{command[2:]}}").is_empty());
+		assert!(
+			check_suggest("#[executable(git), !err_contains(a|b)]\ngit {{command[1:]}}").is_empty()
+		);
+		assert!(check_suggest("{{typo[1]({{shell(git branch)}})}}").is_empty());
+
+		let problems = |suggest| {
+			check_suggest(suggest)
+				.into_iter()
+				.map(|(_, message)| message)
+				.collect::<Vec<String>>()
+		};
+		assert_eq!(
+			problems("#[executable(git), err_contain(x)]\ngit"),
+			["unknown condition `err_contain`"]
+		);
+		assert_eq!(
+			problems("{{command[1:]} {{typo[x](a, b)}}"),
+			[
+				"bad typo index `[x]`",
+				"placeholder is not closed with `}}`"
+			]
+		);
+		assert_eq!(
+			problems("#[cmd_contains([a-z)]\n{{err::(unclosed}}"),
+			[
+				"invalid regex `[a-z`: found unclosed character class",
+				"invalid regex `(unclosed`: found open group without closing ')'"
+			]
+		);
+		assert_eq!(
+			problems("{{select[0](path}}"),
+			["`select` requires candidates in parentheses"]
+		);
+	}
+}
@@ -0,0 +1,95 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use pay_respects_utils::evals::regex_match;
+use pay_respects_utils::strings::split_unescaped_character;
+
+use crate::Context;
+
+pub const CONDITIONS: [&str; 7] = [
+	"executable",
+	"err_contains",
+	"cmd_contains",
+	"min_length",
+	"length",
+	"max_length",
+	"shell",
+];
+
+/// Identifiers are written along conditions but are not evaluated
+pub const IDENTIFIERS: [&str; 2] = ["INLINE", "FUNCTION"];
+
+#[derive(Debug, PartialEq)]
+pub struct Condition {
+	pub name: String,
+	pub arg: String,
+	pub negated: bool,
+}
+
+/// Splits the `#[...]` line(s) from a suggestion
+/// Returns the suggestion without conditions, and the conditions if any
+pub fn split_conditions(suggest: &str) -> (String, Option<Vec<String>>) {
+	if suggest.starts_with('#') {
+		let mut lines = suggest.lines().collect::<Vec<&str>>();
+		let mut conditions = String::new();
+		for (i, line) in lines[0..].iter().enumerate() {
+			conditions.push_str(line);
+			if line.ends_with(']') {
+				lines = lines[i + 1..].to_vec();
+				break;
+			}
+		}
+
+		let conditions = conditions
+			.trim_start_matches(['#', '['])
+			.trim_end_matches(']');
+		let conditions = split_unescaped_character(conditions, ',')
+			.into_iter()
+			.map(|x| x.trim().to_string())
+			.collect::<Vec<String>>();
+		let suggest = lines.join("\n");
+		return (suggest, Some(conditions));
+	}
+	(suggest.to_owned(), None)
+}
+
+impl Condition {
+	pub fn parse(condition: &str) -> Result<Condition, String> {
+		let Some((name, arg)) = condition.split_once('(') else {
+			return Err(format!("malformed condition `{}`", condition));
+		};
+		// remove only the last character which is ')'
+		// other ')' are kept for regex
+		let Some(arg) = arg.strip_suffix(')') else {
+			return Err(format!("condition `{}` is not closed with `)`", condition));
+		};
+		let name = name.trim();
+		let negated = name.starts_with('!');
+		let name = name.trim_start_matches('!');
+		if !CONDITIONS.contains(&name) {
+			return Err(format!("unknown condition `{}`", name));
+		}
+		Ok(Condition {
+			name: name.to_string(),
+			arg: arg.to_string(),
+			negated,
+		})
+	}
+
+	pub fn eval(&self, context: &Context, error_lower: &str, split: &[String]) -> bool {
+		let arg = self.arg.as_str();
+		let length = || arg.parse::<usize>().ok();
+		let result = match self.name.as_str() {
+			"executable" => context.executables.contains(&arg.to_string()),
+			"err_contains" => regex_match(arg, error_lower),
+			"cmd_contains" => regex_match(arg, context.command),
+			"min_length" => length().is_some_and(|length| split.len() >= length),
+			"length" => length().is_some_and(|length| split.len() == length),
+			"max_length" => length().is_some_and(|length| split.len() <= length + 1),
+			"shell" => context.shell == arg,
+			_ => false,
+		};
+		result != self.negated
+	}
+}
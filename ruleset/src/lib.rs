@@ -0,0 +1,309 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Rule file schema and interpreter, shared by the compile time parser, the
+//! core and the runtime rules module
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use pay_respects_utils::evals::split_command;
+
+mod check;
+mod conditions;
+mod replaces;
+
+pub use check::check_suggest;
+pub use conditions::{CONDITIONS, Condition, IDENTIFIERS, split_conditions};
+pub use replaces::{Expansions, eval_suggest, eval_suggest_explained};
+
+#[derive(serde::Deserialize)]
+pub struct Rule {
+	/// File the rule was read from
+	#[serde(skip)]
+	pub path: String,
+	/// File stem, which is the command the rule is looked up by
+	#[serde(skip)]
+	pub name: String,
+	#[serde(default)]
+	pub command: String,
+	pub extends: Option<Vec<String>>,
+	pub match_err: Vec<MatchError>,
+	#[serde(default)]
+	pub test: Vec<RuleTest>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct MatchError {
+	pub pattern: Option<Vec<String>>,
+	pub suggest: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct RuleTest {
+	pub command: String,
+	pub error: String,
+	#[serde(default)]
+	pub executables: Vec<String>,
+	pub shell: Option<String>,
+	pub expected: String,
+}
+
+/// The failed command a rule is evaluated against
+pub struct Context<'a> {
+	pub shell: &'a str,
+	pub command: &'a str,
+	pub error: &'a str,
+	pub executables: &'a [String],
+	/// Only evaluate `INLINE` suggestions, without an error message
+	pub inline: bool,
+}
+
+/// A suggestion produced by a rule, with what made it match
+pub struct RuleMatch {
+	pub command: String,
+	/// Pattern as written in the rule
+	pub pattern: Option<String>,
+	pub conditions: Vec<String>,
+	/// Placeholders and the values they expanded to
+	pub expansions: Expansions,
+}
+
+fn rule_name(path: &Path) -> String {
+	path.file_stem()
+		.map(|stem| stem.to_string_lossy().to_string())
+		.unwrap_or_default()
+}
+
+fn normalize(string: &str) -> String {
+	string
+		.split_whitespace()
+		.collect::<Vec<_>>()
+		.join("")
+		.to_lowercase()
+}
+
+impl Rule {
+	pub fn from_file(path: &str) -> Result<Rule, String> {
+		let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+		Rule::parse(path, &content)
+	}
+
+	/// Parses the `content` of the rule file at `path`
+	pub fn parse(path: &str, content: &str) -> Result<Rule, String> {
+		let mut rule: Rule = toml::from_str(content).map_err(|e| e.to_string())?;
+		rule.path = path.to_string();
+		rule.name = rule_name(Path::new(path));
+		Ok(rule)
+	}
+
+	fn extends(&self, executable: &str) -> bool {
+		self.extends.iter().flatten().any(|x| x == executable)
+	}
+
+	/// Suggestions of the first matching pattern of each `[[match_err]]`.
+	/// `FUNCTION` suggestions are only available to compiled rules.
+	pub fn suggestions(&self, context: &Context) -> Vec<RuleMatch> {
+		let split = split_command(context.command);
+		let error_lower = normalize(context.error);
+
+		let mut matches = vec![];
+		for match_err in &self.match_err {
+			let patterns = match (&match_err.pattern, context.inline) {
+				(Some(patterns), false) => patterns
+					.iter()
+					.map(|pattern| (normalize(pattern), Some(pattern.clone())))
+					.collect(),
+				_ => vec![("".to_string(), None)],
+			};
+			for (normalized, pattern) in patterns {
+				if !error_lower.contains(&normalized) {
+					continue;
+				}
+				for suggest in &match_err.suggest {
+					let Some((suggest, conditions)) =
+						self.eval_conditions(suggest, context, &error_lower, &split)
+					else {
+						continue;
+					};
+					let (suggests, expansions) = eval_suggest_explained(&suggest, context);
+					matches.extend(suggests.into_iter().map(|command| RuleMatch {
+						command,
+						pattern: pattern.clone(),
+						conditions: conditions.clone(),
+						expansions: expansions.clone(),
+					}));
+				}
+				break;
+			}
+		}
+		matches
+	}
+
+	/// Returns the suggestion without conditions if they all hold
+	fn eval_conditions(
+		&self,
+		suggest: &str,
+		context: &Context,
+		error_lower: &str,
+		split: &[String],
+	) -> Option<(String, Vec<String>)> {
+		let (suggest, conditions) = split_conditions(suggest);
+		let mut conditions = conditions.unwrap_or_default();
+		if conditions.iter().any(|c| c == "FUNCTION")
+			|| (context.inline && !conditions.iter().any(|c| c == "INLINE"))
+		{
+			return None;
+		}
+		conditions.retain(|c| !IDENTIFIERS.contains(&c.as_str()));
+
+		for condition in &conditions {
+			let condition = match Condition::parse(condition) {
+				Ok(condition) => condition,
+				Err(e) => {
+					eprintln!(
+						"{}: {}, run `pay-respects check-rules` for details",
+						self.path, e
+					);
+					return None;
+				}
+			};
+			// there is no error message when inline
+			if context.inline && condition.name == "err_contains" {
+				continue;
+			}
+			if !condition.eval(context, error_lower, split) {
+				return None;
+			}
+		}
+		Some((suggest, conditions))
+	}
+}
+
+/// Rules read from rule directories, by the command they are for
+#[derive(Default)]
+pub struct RuleSet {
+	rules: HashMap<String, Rule>,
+}
+
+impl RuleSet {
+	/// Reads every rule file in `dirs`, earlier directories take priority
+	pub fn load(dirs: &[String]) -> RuleSet {
+		RuleSet::load_matching(dirs, |_, _| true)
+	}
+
+	/// Reads only the rules `suggestions` uses for `executable`: its own, those
+	/// extending it and the general rule. Other files are not parsed unless
+	/// they mention `executable`.
+	pub fn load_for(dirs: &[String], executable: &str) -> RuleSet {
+		let wanted = |name: &str| name == executable || name == "_PR_GENERAL";
+		let quoted = [format!("\"{}\"", executable), format!("'{}'", executable)];
+		let mut set = RuleSet::load_matching(dirs, |name, content| {
+			wanted(name) || quoted.iter().any(|quoted| content.contains(quoted))
+		});
+		set.rules
+			.retain(|name, rule| wanted(name) || rule.extends(executable));
+		set
+	}
+
+	/// Reads the rule files in `dirs` whose name and content are accepted by
+	/// `matches`
+	fn load_matching(dirs: &[String], matches: impl Fn(&str, &str) -> bool) -> RuleSet {
+		let mut rules = HashMap::new();
+		// also those not accepted, as they still shadow later directories
+		let mut seen = HashSet::new();
+		for dir in dirs {
+			let Ok(entries) = std::fs::read_dir(dir) else {
+				continue;
+			};
+			for entry in entries.filter_map(|entry| entry.ok()) {
+				let path = entry.path();
+				if path.extension().is_none_or(|ext| ext != "toml") {
+					continue;
+				}
+				let name = rule_name(&path);
+				if !seen.insert(name.clone()) {
+					continue;
+				}
+				let path = path.to_string_lossy().to_string();
+				let rule = std::fs::read_to_string(&path)
+					.map_err(|e| e.to_string())
+					.and_then(|content| match matches(&name, &content) {
+						true => Rule::parse(&path, &content).map(Some),
+						false => Ok(None),
+					});
+				match rule {
+					Ok(Some(rule)) => {
+						rules.insert(name, rule);
+					}
+					Ok(None) => {}
+					Err(e) => eprintln!("Failed to read rule {}: {}", path, e),
+				}
+			}
+		}
+		RuleSet { rules }
+	}
+
+	pub fn get(&self, name: &str) -> Option<&Rule> {
+		self.rules.get(name)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+
+	/// Suggestions of the rule for `executable`, the rules extended to it
+	/// (as compiled rules do), and the general rule
+	pub fn suggestions(&self, executable: &str, context: &Context) -> Vec<(&Rule, RuleMatch)> {
+		let mut extended = self
+			.rules
+			.values()
+			.filter(|rule| rule.name != executable)
+			.filter(|rule| rule.extends(executable))
+			.collect::<Vec<&Rule>>();
+		extended.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+
+		let mut matches = vec![];
+		for rule in self.get(executable).into_iter().chain(extended) {
+			matches.extend(rule.suggestions(context).into_iter().map(|m| (rule, m)));
+		}
+		if executable != "_PR_GENERAL"
+			&& let Some(rule) = self.get("_PR_GENERAL")
+		{
+			matches.extend(rule.suggestions(context).into_iter().map(|m| (rule, m)));
+		}
+		matches
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::RuleSet;
+
+	#[test]
+	fn test_load_for() {
+		let dir = std::env::temp_dir().join(format!("pay-respects-ruleset-{}", std::process::id()));
+		let (first, second) = (dir.join("first"), dir.join("second"));
+		std::fs::create_dir_all(&first).unwrap();
+		std::fs::create_dir_all(&second).unwrap();
+		let rule = |dir: &std::path::Path, name: &str, extends: &str| {
+			let content = format!("extends = [{}]\n[[match_err]]\nsuggest = [\"\"]\n", extends);
+			std::fs::write(dir.join(format!("{}.toml", name)), content).unwrap();
+		};
+		rule(&first, "git", "");
+		rule(&first, "hub", "\"git\"");
+		rule(&first, "ls", "");
+		rule(&first, "_PR_GENERAL", "");
+		// shadowed by the rule of the first directory, which does not extend
+		rule(&second, "ls", "\"git\"");
+		std::fs::write(first.join("broken.toml"), "not a rule").unwrap();
+
+		let dirs = [first, second].map(|dir| dir.to_string_lossy().to_string());
+		let set = RuleSet::load_for(&dirs, "git");
+		let mut names = set.rules.keys().collect::<Vec<&String>>();
+		names.sort();
+		assert_eq!(names, ["_PR_GENERAL", "git", "hub"]);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,312 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::ops::Range;
+
+use pay_respects_utils::evals::*;
+
+use crate::Context;
+
+fn tag(name: &str, x: i32) -> String {
+	format!("{{{}{}}}", name, x)
+}
+
+/// Placeholders as written in the rule, and what they were replaced with
+pub type Expansions = Vec<(String, String)>;
+
+/// Evaluates the placeholders of a suggestion (without conditions)
+/// Returns a suggestion per selection if `{{select}}` is used
+pub fn eval_suggest(suggest: &str, context: &Context) -> Vec<String> {
+	eval_suggest_explained(suggest, context).0
+}
+
+/// Same as `eval_suggest`, along with the value of each placeholder. Compiled
+/// rules are evaluated with it as well.
+pub fn eval_suggest_explained(suggest: &str, context: &Context) -> (Vec<String>, Expansions) {
+	let mut suggest = suggest.to_owned();
+	let mut last_command = context.command.to_owned();
+	let mut opt_list = Vec::new();
+	let mut expansions = Vec::new();
+
+	opts(
+		&mut suggest,
+		&mut last_command,
+		&mut opt_list,
+		&mut expansions,
+	);
+	let split = split_command(&last_command);
+	if suggest.contains("{{command}}") {
+		suggest = suggest.replace("{{command}}", &last_command);
+	}
+
+	cmd_reg(&mut suggest, &last_command, &mut expansions);
+	err(&mut suggest, context.error, &mut expansions);
+	command(&mut suggest, &split, &mut expansions);
+	shell(&mut suggest, context.shell, &mut expansions);
+	typo(&mut suggest, &split, context.executables, &mut expansions);
+
+	let mut select_list = Vec::new();
+	select(&mut suggest, &split, context.executables, &mut select_list);
+
+	for (tag, value) in opt_list {
+		suggest = suggest.replace(&tag, &value);
+	}
+
+	if select_list.is_empty() {
+		return (vec![suggest], expansions);
+	}
+	let suggests = select_list
+		.iter()
+		.map(|selection| suggest.replace("{{selection}}", selection))
+		.collect();
+	(suggests, expansions)
+}
+
+/// Records the placeholder at `range` of `suggest`, with its whitespace
+/// collapsed
+fn expand(expansions: &mut Expansions, suggest: &str, range: &Range<usize>, value: &str) {
+	let source = suggest[range.clone()]
+		.split_whitespace()
+		.collect::<Vec<&str>>()
+		.join(" ");
+	expansions.push((source, value.to_string()));
+}
+
+fn eval_placeholder(string: &str, start: &str, end: &str) -> (Range<usize>, Range<usize>) {
+	let start_index = string.find(start).unwrap();
+	let end_index = match string[start_index..].find(end) {
+		Some(index) => index + start_index + end.len(),
+		None => string.len(),
+	};
+
+	let placeholder = start_index..end_index;
+
+	let args = start_index + start.len()..(end_index - end.len()).max(start_index + start.len());
+
+	(placeholder, args)
+}
+
+/// Resolves a possibly negative index, or `None` if out of bounds
+fn index(index: i32, len: usize) -> Option<usize> {
+	let index = if index < 0 { len as i32 + index } else { index };
+	(index >= 0 && (index as usize) < len).then_some(index as usize)
+}
+
+/// Resolves `n` or `start:end` (both inclusive and optional) into a range
+fn range(range: &str, len: usize) -> Option<Range<usize>> {
+	match range.split_once(':') {
+		Some((start, end)) => {
+			let start = start.trim().parse::<i32>().unwrap_or(0);
+			let start = if start < 0 { len as i32 + start } else { start };
+			let end = match end.trim().parse::<i32>() {
+				Ok(end) if end < 0 => len as i32 + end + 1,
+				Ok(end) => end + 1,
+				Err(_) => len as i32,
+			};
+			let start = start.clamp(0, len as i32) as usize;
+			let end = end.clamp(0, len as i32) as usize;
+			Some(start..end.max(start))
+		}
+		None => {
+			let i = index(range.trim().parse::<i32>().ok()?, len)?;
+			Some(i..i + 1)
+		}
+	}
+}
+
+/// Candidates inside the parentheses of `typo` and `select`
+pub(crate) fn list(args: &str) -> Vec<String> {
+	let list = match args.split_once('(') {
+		Some((_, list)) => list.rsplit_once(')').map(|x| x.0).unwrap_or(list),
+		None => return vec![],
+	};
+	list.split([',', '\n'])
+		.map(|s| s.trim().to_string())
+		.filter(|s| !s.is_empty())
+		.collect()
+}
+
+/// Index inside the brackets of `typo` and `select`
+pub(crate) fn bracket(args: &str) -> Option<&str> {
+	let (_, rest) = args.split_once('[')?;
+	Some(rest.split_once(']')?.0)
+}
+
+fn opts(
+	suggest: &mut String,
+	last_command: &mut String,
+	opt_list: &mut Vec<(String, String)>,
+	expansions: &mut Expansions,
+) {
+	let mut replace_tag = 0;
+	let tag_name = "opts";
+
+	while suggest.contains(" {{opt::") {
+		let (placeholder, args) = eval_placeholder(suggest, " {{opt::", "}}");
+
+		let opt = &suggest[args.to_owned()];
+		let regex = opt.trim();
+		let current_tag = tag(tag_name, replace_tag);
+
+		let opts = {
+			let caps = opt_regex(regex, last_command);
+			if caps.is_empty() {
+				"".to_string()
+			} else {
+				format!(" {}", caps)
+			}
+		};
+
+		expand(expansions, suggest, &placeholder, &opts);
+		opt_list.push((current_tag.clone(), opts));
+		suggest.replace_range(placeholder, &current_tag);
+
+		replace_tag += 1;
+	}
+}
+
+fn cmd_reg(suggest: &mut String, last_command: &str, expansions: &mut Expansions) {
+	while suggest.contains("{{cmd::") {
+		let (placeholder, args) = eval_placeholder(suggest, "{{cmd::", "}}");
+
+		let regex = suggest[args.to_owned()].trim();
+
+		let command = cmd_regex(regex, last_command);
+		expand(expansions, suggest, &placeholder, &command);
+		suggest.replace_range(placeholder, &command)
+	}
+}
+
+fn err(suggest: &mut String, error_msg: &str, expansions: &mut Expansions) {
+	while suggest.contains("{{err::") {
+		let (placeholder, args) = eval_placeholder(suggest, "{{err::", "}}");
+
+		let regex = suggest[args.to_owned()].trim();
+
+		let command = err_regex(regex, error_msg);
+		expand(expansions, suggest, &placeholder, &command);
+		suggest.replace_range(placeholder, &command)
+	}
+}
+
+fn command(suggest: &mut String, split: &[String], expansions: &mut Expansions) {
+	while suggest.contains("{{command") {
+		let (placeholder, args) = eval_placeholder(suggest, "{{command", "}}");
+
+		let args = suggest[args.to_owned()].trim_matches(|c| c == '[' || c == ']');
+		let command = range(args, split.len())
+			.map(|range| split[range].join(" "))
+			.unwrap_or_default();
+
+		expand(expansions, suggest, &placeholder, &command);
+		suggest.replace_range(placeholder, &command);
+	}
+}
+
+fn typo(
+	suggest: &mut String,
+	split: &[String],
+	executables: &[String],
+	expansions: &mut Expansions,
+) {
+	while suggest.contains("{{typo") {
+		let (placeholder, args) = eval_placeholder(suggest, "{{typo", "}}");
+		let args = &suggest[args.to_owned()];
+
+		let range = bracket(args).and_then(|index| range(index, split.len()));
+		let match_list = list(args);
+
+		let command = match range {
+			Some(range) if match_list.len() > 1 || is_keyword(&match_list) => {
+				suggest_typo(&split[range], &match_list, executables)
+			}
+			// e.g. a `{{shell(...)}}` list that printed a single line
+			Some(range) => split[range]
+				.iter()
+				.map(|typo| find_similar(typo, &match_list).unwrap_or(typo.to_string()))
+				.collect::<Vec<String>>()
+				.join(" "),
+			None => "".to_string(),
+		};
+
+		expand(expansions, suggest, &placeholder, &command);
+		suggest.replace_range(placeholder, &command);
+	}
+}
+
+/// Single candidates reserved for specific matches
+fn is_keyword(match_list: &[String]) -> bool {
+	match_list.len() == 1 && (match_list[0] == "path" || match_list[0] == "file")
+}
+
+fn select(
+	suggest: &mut String,
+	split: &[String],
+	executables: &[String],
+	select_list: &mut Vec<String>,
+) {
+	if suggest.contains("{{select") {
+		let (placeholder, args) = eval_placeholder(suggest, "{{select", "}}");
+		let args = &suggest[args.to_owned()];
+
+		let index = bracket(args)
+			.and_then(|i| i.trim().parse::<i32>().ok())
+			.and_then(|i| index(i, split.len()))
+			.unwrap_or(0);
+		let selection_list = list(args);
+
+		if selection_list.len() == 1 && selection_list[0] == "path" {
+			if let Some(word) = split.get(index) {
+				let selects = best_matches(word, executables).unwrap_or(vec![word.clone()]);
+				select_list.extend(selects);
+			}
+		} else {
+			select_list.extend(selection_list);
+		}
+
+		let tag = "{{selection}}";
+		let placeholder = suggest[placeholder.clone()].to_owned();
+		*suggest = suggest.replace(&placeholder, tag);
+	}
+}
+
+fn shell(suggest: &mut String, shell: &str, expansions: &mut Expansions) {
+	while suggest.contains("{{shell") {
+		let (placeholder, args) = eval_placeholder(suggest, "{{shell", "}}");
+		let range = suggest[args.to_owned()].trim_matches(|c| c == '(' || c == ')');
+
+		let command = eval_shell_command(shell, range);
+
+		expand(expansions, suggest, &placeholder, &command.join(", "));
+		suggest.replace_range(placeholder, &command.join("\n"));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::eval_suggest;
+	use crate::Context;
+
+	#[test]
+	fn test_eval_suggest() {
+		let executables = vec!["git".to_string(), "grep".to_string()];
+		let context = Context {
+			shell: "bash",
+			command: "gti comit -m msg",
+			error: "",
+			executables: &executables,
+			inline: false,
+		};
+		let eval = |suggest| eval_suggest(suggest, &context);
+
+		assert_eq!(eval("{{command[-1]}} {{command[1:2]}}"), ["msg comit -m"]);
+		assert_eq!(eval("x{{command[9]}}"), ["x"]);
+		assert_eq!(eval("{{typo[1](commit, clone)}}"), ["commit"]);
+		assert_eq!(eval("{{typo[1](commit)}}"), ["commit"]);
+		assert_eq!(
+			eval("{{select[0](path)}} {{command[1:]}}"),
+			["git comit -m msg"]
+		);
+	}
+}
@@ -10,6 +10,8 @@ use crossterm::{
 	terminal::{self, ClearType},
 };
 use std::io::{Write, stderr};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
 
 #[derive(Default)]
 struct Page {
@@ -21,14 +23,68 @@ struct Page {
 use crossterm::event::KeyEventKind;
 
 const MAX_ITEMS: usize = 10;
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+
+/// An item of `select_streamed`
+pub struct Item {
+	pub active: String,
+	pub inactive: String,
+	/// Toggled with `?`
+	pub details: Option<String>,
+}
+
+/// Sent to `select_streamed` while it is open
+pub enum Update {
+	/// Items appended after the others
+	Items(Vec<Item>),
+	/// Shown with a spinner until `Done` is sent with the same name
+	Pending(String),
+	Done(String),
+}
 
 pub fn select(
 	prelude: &str,
 	active_items: &[String],
 	inactive_items: &[String],
 ) -> Result<usize, Box<dyn std::error::Error>> {
+	select_explained(prelude, active_items, inactive_items, None)?
+		.ok_or_else(|| "no items to select".into())
+}
+
+/// Same as `select`, with `?` toggling the details of the current item.
+/// Returns `None` if there are no items.
+pub fn select_explained(
+	prelude: &str,
+	active_items: &[String],
+	inactive_items: &[String],
+	details: Option<&[String]>,
+) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+	let items = active_items
+		.iter()
+		.zip(inactive_items)
+		.enumerate()
+		.map(|(i, (active, inactive))| Item {
+			active: active.clone(),
+			inactive: inactive.clone(),
+			details: details.and_then(|details| details.get(i).cloned()),
+		})
+		.collect::<Vec<Item>>();
+	// no updates, the sender is dropped right away
+	let (_, updates) = mpsc::channel();
+	select_streamed(&|_| prelude.to_string(), items, updates)
+}
+
+/// Same as `select_explained`, with items appended and pending sources shown
+/// as they are received from `updates`. The prelude is given the number of
+/// items. Returns `None` if there were no items once every sender is dropped.
+pub fn select_streamed(
+	prelude: &dyn Fn(usize) -> String,
+	items: Vec<Item>,
+	updates: Receiver<Update>,
+) -> Result<Option<usize>, Box<dyn std::error::Error>> {
 	let height = terminal::size()?.1 as usize;
-	let prelude_lines = prelude.lines().count();
+	let prelude_lines = prelude(0).lines().count();
 
 	if height < prelude_lines + 1 {
 		terminal::disable_raw_mode()?;
@@ -36,145 +92,121 @@ pub fn select(
 		std::process::exit(1);
 	}
 
-	let pages = get_pages(active_items, height - prelude_lines - 2);
-	let active_items = add_padding(active_items, 5);
-	let inactive_items = add_padding(inactive_items, 5);
+	let mut view = View {
+		height: height - prelude_lines,
+		..Default::default()
+	};
+	let mut connected = true;
+	view.push(items);
+	view.receive(&updates, &mut connected);
+	view.paginate();
 
 	terminal::enable_raw_mode()?;
 	execute!(stderr(), terminal::DisableLineWrap)?;
 	drain_input();
 
 	execute!(stderr(), cursor::Hide)?;
-	eprint!("{}\r\n", prelude);
+	view.draw(&prelude(view.items.len()))?;
 
-	let mut current = 0;
-	let mut page_idx = 0;
-	let total_pages = pages.len();
+	loop {
+		if view.items.is_empty() && view.pending.is_empty() && !connected {
+			cleanup(view.lines + prelude_lines)?;
+			terminal::disable_raw_mode()?;
+			return Ok(None);
+		}
 
-	macro_rules! page_range {
-		() => {
-			pages[page_idx].items[0]..pages[page_idx].items[pages[page_idx].items.len() - 1] + 1
-		};
-	}
-	macro_rules! page_len {
-		() => {
-			pages[page_idx].items.len()
-		};
-	}
-	macro_rules! next_page {
-		() => {
-			page_idx = (page_idx + 1) % pages.len();
-			current = 0;
+		let waiting = connected || !view.pending.is_empty();
+		let timeout = match waiting {
+			true => SPINNER_INTERVAL,
+			false => Duration::from_secs(60),
 		};
-	}
-	macro_rules! prev_page {
-		() => {
-			page_idx = if page_idx == 0 {
-				pages.len() - 1
-			} else {
-				page_idx - 1
-			};
-			current = pages[page_idx].items.len() - 1;
-		};
-	}
-	macro_rules! clear_lines {
-		() => {
-			if pages.len() == 1 {
-				pages[page_idx].lines
-			} else {
-				pages[page_idx].lines + 1
-			}
-		};
-	}
-
-	// Initial draw
-	draw(
-		&active_items[page_range!()],
-		&inactive_items[page_range!()],
-		current,
-		page_idx,
-		total_pages,
-	)?;
-
-	loop {
-		if let Event::Key(key) = event::read()? {
-			// somehow windows receives two events
-			#[cfg(target_os = "windows")]
-			if key.kind != KeyEventKind::Press {
-				continue;
-			}
+		let mut changed = false;
+		if event::poll(timeout)? {
+			if let Event::Key(key) = event::read()? {
+				// somehow windows receives two events
+				#[cfg(target_os = "windows")]
+				if key.kind != KeyEventKind::Press {
+					continue;
+				}
 
-			let clear_lines = clear_lines!();
-			match key.code {
-				// Navigation keys
-				KeyCode::Char('j') | KeyCode::Down => {
-					// current = (current + 1) % active_items.len();
-					if current + 1 >= page_len!() {
-						next_page!();
-					} else {
-						current += 1;
+				changed = true;
+				match key.code {
+					// Quit keys
+					KeyCode::Char('c') | KeyCode::Char('d')
+						if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
+					{
+						cleanup(view.lines)?;
+						quit();
 					}
-				}
-				KeyCode::Char('k') | KeyCode::Up => {
-					if current == 0 {
-						prev_page!();
-					} else {
-						current -= 1
-					};
-				}
-				// Page navigation keys
-				KeyCode::Char('f') | KeyCode::PageDown => {
-					next_page!();
-				}
-				KeyCode::Char('b') | KeyCode::PageUp => {
-					prev_page!();
-				}
-				// Shortcut keys (1-0)
-				KeyCode::Char(c) if c.is_ascii_digit() => {
-					if c == '0' {
-						if page_len!() == 10 {
-							current = MAX_ITEMS - 1;
+					KeyCode::Esc | KeyCode::Char('q') => {
+						cleanup(view.lines + prelude_lines)?;
+						quit()
+					}
+					// nothing to select yet
+					_ if view.items.is_empty() => {}
+					// Navigation keys
+					KeyCode::Char('j') | KeyCode::Down => {
+						if view.current + 1 >= view.page_len() {
+							view.next_page();
+						} else {
+							view.current += 1;
 						}
-						break;
 					}
-					let idx = c.to_digit(10).unwrap() as usize - 1;
-					if idx < pages[page_idx].items.len() {
-						current = idx;
-						break;
+					KeyCode::Char('k') | KeyCode::Up => {
+						if view.current == 0 {
+							view.prev_page();
+						} else {
+							view.current -= 1
+						};
 					}
-				}
-				// Quit keys
-				KeyCode::Char('c') | KeyCode::Char('d') => {
-					if key.modifiers.contains(event::KeyModifiers::CONTROL) {
-						cleanup(pages[page_idx].lines)?;
-						quit();
+					// Page navigation keys
+					KeyCode::Char('f') | KeyCode::PageDown => {
+						view.next_page();
 					}
+					KeyCode::Char('b') | KeyCode::PageUp => {
+						view.prev_page();
+					}
+					// Shortcut keys (1-0)
+					KeyCode::Char(c) if c.is_ascii_digit() => {
+						if c == '0' {
+							if view.page_len() == 10 {
+								view.current = MAX_ITEMS - 1;
+							}
+							break;
+						}
+						let idx = c.to_digit(10).unwrap() as usize - 1;
+						if idx < view.page_len() {
+							view.current = idx;
+							break;
+						}
+					}
+					KeyCode::Char('?') => {
+						view.explain = !view.explain;
+					}
+					KeyCode::Enter => break,
+					_ => {}
 				}
-				KeyCode::Esc | KeyCode::Char('q') => {
-					cleanup(clear_lines!() + prelude_lines)?;
-					quit()
-				}
-				KeyCode::Enter => break,
-				_ => {}
 			}
+			drain_input();
+		} else if !view.pending.is_empty() {
+			view.frame += 1;
+			changed = true;
+		}
 
-			redraw(
-				&active_items[page_range!()],
-				&inactive_items[page_range!()],
-				current,
-				clear_lines,
-				page_idx,
-				total_pages,
-			)?;
+		if view.receive(&updates, &mut connected) {
+			view.paginate();
+			changed = true;
+		}
+		if changed {
+			view.redraw(&prelude(view.items.len()), prelude_lines)?;
 		}
-		drain_input();
 	}
 
 	// Cleanup
-	cleanup(clear_lines!() + prelude_lines)?;
+	cleanup(view.lines + prelude_lines)?;
 	terminal::disable_raw_mode()?;
 
-	Ok(pages[page_idx].items[current])
+	Ok(Some(view.selected()))
 }
 
 pub fn select_simple(prelude: &str, items: &[String]) -> Result<usize, Box<dyn std::error::Error>> {
@@ -223,68 +255,190 @@ fn print(str: &str) {
 	eprint!("{} \r\n", str);
 }
 
-fn draw(
-	active_items: &[String],
-	inactive_items: &[String],
-	selected: usize,
-	current_page: usize,
-	total_pages: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
-	for (i, item) in active_items.iter().enumerate() {
-		execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
-		if i == selected {
-			let prefix = format!("> {}) ", select_idx(i)).cyan().bold();
-			let line = format!("{}{}", prefix, item);
-			print(&line);
+/// State of `select_streamed`
+#[derive(Default)]
+struct View {
+	items: Vec<Item>,
+	pending: Vec<String>,
+	pages: Vec<Page>,
+	/// Lines below the prelude
+	height: usize,
+	/// Lines of the longest item and details
+	item_lines: usize,
+	details_lines: usize,
+	page_idx: usize,
+	current: usize,
+	explain: bool,
+	frame: usize,
+	/// Lines drawn after the prelude
+	lines: usize,
+}
+
+impl View {
+	fn push(&mut self, items: Vec<Item>) {
+		for item in &items {
+			self.item_lines = self.item_lines.max(item.active.lines().count());
+			let details = item.details.as_deref().map_or(0, |x| x.lines().count());
+			self.details_lines = self.details_lines.max(details);
+		}
+		self.items.extend(items.into_iter().map(|item| Item {
+			active: add_padding(&item.active, 5),
+			inactive: add_padding(&item.inactive, 5),
+			details: item.details.map(|details| add_padding(&details, 5)),
+		}));
+	}
+
+	/// Returns whether anything was received
+	fn receive(&mut self, updates: &Receiver<Update>, connected: &mut bool) -> bool {
+		let mut changed = false;
+		while *connected {
+			match updates.try_recv() {
+				Ok(Update::Items(items)) => self.push(items),
+				Ok(Update::Pending(name)) => self.pending.push(name),
+				Ok(Update::Done(name)) => self.pending.retain(|pending| pending != &name),
+				Err(TryRecvError::Empty) => break,
+				Err(TryRecvError::Disconnected) => {
+					*connected = false;
+					self.pending.clear();
+				}
+			}
+			changed = true;
+		}
+		changed
+	}
+
+	/// Fits the pages between the prelude and the page number, details and
+	/// pending sources, keeping the selected item selected
+	fn paginate(&mut self) {
+		let selected = self
+			.pages
+			.get(self.page_idx)
+			.and_then(|page| page.items.get(self.current))
+			.copied()
+			.unwrap_or(0);
+		let available = self.height.saturating_sub(2 + self.pending.len());
+		// items are kept whole if the details don't fit along
+		let max_height = available
+			.saturating_sub(self.details_lines)
+			.max(self.item_lines.min(available))
+			.max(1);
+
+		let active_items = self
+			.items
+			.iter()
+			.map(|item| item.active.clone())
+			.collect::<Vec<String>>();
+		self.pages = get_pages(&active_items, max_height);
+		(self.page_idx, self.current) = (0, 0);
+		for (page_idx, page) in self.pages.iter().enumerate() {
+			if let Some(current) = page.items.iter().position(|&idx| idx == selected) {
+				(self.page_idx, self.current) = (page_idx, current);
+			}
+		}
+	}
+
+	fn page_len(&self) -> usize {
+		self.pages[self.page_idx].items.len()
+	}
+
+	fn next_page(&mut self) {
+		self.page_idx = (self.page_idx + 1) % self.pages.len();
+		self.current = 0;
+	}
+
+	fn prev_page(&mut self) {
+		self.page_idx = if self.page_idx == 0 {
+			self.pages.len() - 1
 		} else {
-			let prefix = format!("  {}) ", select_idx(i)).cyan();
-			let line = format!("{}{}", prefix, inactive_items.get(i).unwrap());
-			print(&line);
+			self.page_idx - 1
+		};
+		self.current = self.page_len() - 1;
+	}
+
+	fn selected(&self) -> usize {
+		self.pages[self.page_idx].items[self.current]
+	}
+
+	fn draw(&mut self, prelude: &str) -> Result<(), Box<dyn std::error::Error>> {
+		for line in prelude.lines() {
+			execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
+			eprint!("{}\r\n", line);
+		}
+
+		let mut lines = 0;
+		if let Some(page) = self.pages.get(self.page_idx) {
+			for (i, idx) in page.items.iter().enumerate() {
+				let item = &self.items[*idx];
+				execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
+				if i == self.current {
+					let prefix = format!("> {}) ", select_idx(i)).cyan().bold();
+					print(&format!("{}{}", prefix, item.active));
+				} else {
+					let prefix = format!("  {}) ", select_idx(i)).cyan();
+					print(&format!("{}{}", prefix, item.inactive));
+				}
+			}
+			lines += page.lines;
+			if self.pages.len() > 1 {
+				execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
+				let page_info = format!("[{}/{}]", self.page_idx + 1, self.pages.len())
+					.cyan()
+					.to_string();
+				print(&page_info);
+				lines += 1;
+			}
+			if self.explain
+				&& let Some(details) = &self.items[self.selected()].details
+			{
+				execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
+				print(&format!("{}{}", " ".repeat(5), details));
+				lines += details.lines().count();
+			}
+		}
+		for name in &self.pending {
+			execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
+			let spinner = SPINNER[self.frame % SPINNER.len()].cyan();
+			print(&format!("  {} {}", spinner, name.dimmed()));
+			lines += 1;
 		}
+		self.lines = lines;
+		stderr().flush()?;
+		Ok(())
 	}
-	if total_pages > 1 {
-		execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
-		let page_info = format!("[{}/{}]", current_page + 1, total_pages)
-			.cyan()
-			.to_string();
-		print(&page_info);
+
+	fn redraw(
+		&mut self,
+		prelude: &str,
+		prelude_lines: usize,
+	) -> Result<(), Box<dyn std::error::Error>> {
+		let lines = self.lines + prelude_lines;
+		move_up(lines)?;
+		for _ in 0..lines {
+			execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
+			eprint!("\r\n");
+		}
+		move_up(lines)?;
+		self.draw(prelude)
 	}
-	stderr().flush()?;
-	Ok(())
 }
 
-fn redraw(
-	active_items: &[String],
-	inactive_items: &[String],
-	selected: usize,
-	lines: usize,
-	currrent_page: usize,
-	total_pages: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
-	execute!(stderr(), cursor::MoveUp(lines as u16))?;
-	for _ in 0..lines {
-		execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
-		eprint!("\r\n");
+/// `MoveUp(0)` moves up a line in some terminals
+fn move_up(lines: usize) -> Result<(), Box<dyn std::error::Error>> {
+	if lines > 0 {
+		execute!(stderr(), cursor::MoveUp(lines as u16))?;
 	}
-	execute!(stderr(), cursor::MoveUp(lines as u16))?;
-	draw(
-		active_items,
-		inactive_items,
-		selected,
-		currrent_page,
-		total_pages,
-	)
+	Ok(())
 }
 
 fn cleanup(lines: usize) -> Result<(), Box<dyn std::error::Error>> {
-	execute!(stderr(), cursor::MoveUp(lines as u16))?;
+	move_up(lines)?;
 
 	for _ in 0..lines {
 		execute!(stderr(), terminal::Clear(ClearType::CurrentLine))?;
 		eprint!("\r\n");
 	}
 
-	execute!(stderr(), cursor::MoveUp(lines as u16))?;
+	move_up(lines)?;
 	stderr().flush()?;
 
 	execute!(stderr(), cursor::Show).unwrap();
@@ -307,9 +461,9 @@ fn drain_input() {
 	}
 }
 
-fn add_padding(vec: &[String], width: usize) -> Vec<String> {
+fn add_padding(item: &str, width: usize) -> String {
 	let spaces = " ".repeat(width);
 	let from = '\n';
 	let to = format!("\r\n{}", spaces);
-	vec.iter().map(|s| s.replace(from, &to)).collect()
+	item.replace(from, &to)
 }
@@ -16,6 +16,7 @@ export _PR_NO_ZOXIDE=1
 export _PR_EXECUTABLES=""
 export _PR_NO_CONFIG=1
 export _PR_NO_MULTIPLEXER=1
+export _PR_NO_HISTORY=1
 
 PASSED=0
 FAILED=0
@@ -11,7 +11,10 @@ use std::cmp::Ordering;
 use std::collections::HashSet;
 
 fn regex_captures(regex: &str, string: &str) -> Vec<String> {
-	let regex = Regex::new(regex).unwrap();
+	let regex = match Regex::new(regex) {
+		Ok(regex) => regex,
+		Err(_) => return vec![],
+	};
 
 	let mut caps = Vec::new();
 	for captures in regex.captures_iter(string) {
@@ -25,8 +28,7 @@ fn regex_captures(regex: &str, string: &str) -> Vec<String> {
 pub fn regex_match(regex: &str, string: &str) -> bool {
 	let regex = regex.split_whitespace().collect::<Vec<&str>>().join("");
 	let string = string.split_whitespace().collect::<Vec<&str>>().join("");
-	let regex = Regex::new(&regex).unwrap();
-	regex.is_match(&string)
+	Regex::new(&regex).is_ok_and(|regex| regex.is_match(&string))
 }
 
 pub fn opt_regex(regex: &str, command: &mut String) -> String {
@@ -224,11 +226,7 @@ pub fn edit_distance_bests(typo: &str, candidates: &[String]) -> Option<Vec<Stri
 		eprintln!("comparing '{typo}' with '{candidate}': distance = {distance}");
 		use std::cmp::Ordering::*;
 		match distance.cmp(&min_distance) {
-			Equal => {
-				if !min_distance_index.is_empty() {
-					min_distance_index.push(i)
-				}
-			}
+			Equal if !min_distance_index.is_empty() => min_distance_index.push(i),
 			Less => {
 				min_distance = distance;
 				min_distance_index.clear();
@@ -166,6 +166,54 @@ fn user_config_path() -> String {
 	format!("{}/pay-respects/config.toml", xdg_config_home)
 }
 
+/// Directory for persistent data, e.g. `$HOME/.local/share/pay-respects`
+pub fn user_data_dir() -> String {
+	#[cfg(windows)]
+	let xdg_data_home = std::env::var("LOCALAPPDATA").unwrap();
+	#[cfg(not(windows))]
+	let xdg_data_home = std::env::var("XDG_DATA_HOME")
+		.unwrap_or_else(|_| std::env::var("HOME").unwrap() + "/.local/share");
+
+	format!("{}/pay-respects", xdg_data_home)
+}
+
+/// Directory for cached data, e.g. `$HOME/.cache/pay-respects`
+pub fn user_cache_dir() -> String {
+	#[cfg(windows)]
+	let xdg_cache_home = std::env::var("LOCALAPPDATA").unwrap() + "/cache";
+	#[cfg(not(windows))]
+	let xdg_cache_home = std::env::var("XDG_CACHE_HOME")
+		.unwrap_or_else(|_| std::env::var("HOME").unwrap() + "/.cache");
+
+	format!("{}/pay-respects", xdg_cache_home)
+}
+
+/// Directories searched for rule files, by priority
+pub fn rule_dirs() -> Vec<String> {
+	#[cfg(windows)]
+	let xdg_config_home = std::env::var("APPDATA").unwrap();
+	#[cfg(not(windows))]
+	let xdg_config_home = std::env::var("XDG_CONFIG_HOME")
+		.unwrap_or_else(|_| std::env::var("HOME").unwrap() + "/.config");
+
+	#[allow(unused_mut)]
+	let mut dirs = vec![xdg_config_home];
+
+	#[cfg(not(windows))]
+	{
+		let xdg_config_dirs = std::env::var("XDG_CONFIG_DIRS").unwrap_or("/etc/xdg".to_owned());
+		dirs.extend(xdg_config_dirs.split(':').map(|dir| dir.to_string()));
+
+		let xdg_data_dirs =
+			std::env::var("XDG_DATA_DIRS").unwrap_or("/usr/local/share:/usr/share".to_owned());
+		dirs.extend(xdg_data_dirs.split(':').map(|dir| dir.to_string()));
+	}
+
+	dirs.into_iter()
+		.map(|dir| format!("{}/pay-respects/rules", dir))
+		.collect()
+}
+
 #[cfg(windows)]
 fn msys2_conv_path(p: &str) -> std::io::Result<String> {
 	std::process::Command::new("cygpath")
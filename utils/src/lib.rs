@@ -7,6 +7,7 @@ pub mod evals;
 pub mod files;
 pub mod lists;
 pub mod modes;
+pub mod redact;
 pub mod settings;
 pub mod shell;
 pub mod strings;
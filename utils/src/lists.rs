@@ -31,6 +31,41 @@ pub fn blocking_commands() -> Vec<&'static str> {
 	]
 }
 
+/// List of commands with side effects that are not re-run to get their error,
+/// the executable followed by the arguments that must be present
+#[rustfmt::skip]
+pub fn side_effect_commands() -> Vec<&'static str> {
+	vec![
+		"cargo publish",
+		"curl --data",
+		"curl --request DELETE",
+		"curl --request PATCH",
+		"curl --request POST",
+		"curl --request PUT",
+		"curl -X DELETE",
+		"curl -X PATCH",
+		"curl -X POST",
+		"curl -X PUT",
+		"curl -d",
+		"dd",
+		"docker push",
+		"git push",
+		"halt",
+		"kill",
+		"killall",
+		"mkfs",
+		"npm publish",
+		"pkill",
+		"poweroff",
+		"reboot",
+		"rm",
+		"shred",
+		"shutdown",
+		"wget --post-data",
+		"wget --post-file",
+	]
+}
+
 /// List of common valid arguments
 #[rustfmt::skip]
 pub fn commond_arguments() -> Vec<&'static str> {
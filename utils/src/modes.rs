@@ -5,10 +5,27 @@ pub enum Mode {
 	Suggestion,
 	Inline,
 	Echo,
+	Json,
+	Explain,
 	NoConfirm,
 	Cnf,
 }
 
+impl Mode {
+	/// Value of `_PR_MODE` for the mode
+	pub fn name(&self) -> &'static str {
+		match self {
+			Mode::Suggestion => "suggest",
+			Mode::Inline => "inline",
+			Mode::Echo => "echo",
+			Mode::Json => "json",
+			Mode::Explain => "explain",
+			Mode::NoConfirm => "noconfirm",
+			Mode::Cnf => "cnf",
+		}
+	}
+}
+
 pub fn run_mode() -> Mode {
 	match std::env::var("_PR_MODE") {
 		Ok(mode) => match mode.as_str() {
@@ -17,6 +34,8 @@ pub fn run_mode() -> Mode {
 			"cnf" => Mode::Cnf,
 			"noconfirm" => Mode::NoConfirm,
 			"echo" => Mode::Echo,
+			"json" => Mode::Json,
+			"explain" => Mode::Explain,
 			_ => {
 				eprintln!("Invalid mode: {}", mode);
 				exit(1);
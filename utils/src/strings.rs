@@ -43,6 +43,21 @@ pub fn remove_color_codes(input: &str) -> String {
 	re.replace_all(input, "").to_string()
 }
 
+/// Error message with its variable parts (quoted text, paths and numbers)
+/// replaced, so the same kind of error gives the same fingerprint
+pub fn error_fingerprint(error: &str) -> String {
+	let regex = regex_lite::Regex::new(r#"`[^`]*`|'[^']*'|"[^"]*"|\S*/\S*|\d+"#).unwrap();
+	let error = error.to_lowercase();
+	let error = regex.replace_all(&error, "_");
+	error
+		.split_whitespace()
+		.collect::<Vec<&str>>()
+		.join(" ")
+		.chars()
+		.take(200)
+		.collect()
+}
+
 /// Replaces all occurrences of the target character in the input string with
 /// the replacement string, but only if the target character is not escaped
 /// by an **odd number** of backslashes.